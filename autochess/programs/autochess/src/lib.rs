@@ -1,13 +1,13 @@
 pub mod state;
 
 use anchor_lang::{prelude::*};
-use state::game::Game;
+use state::{game::{Game, HandMode, HandAdjustments}, merkle::{CardReveal, AssetReveal}, collection::{CollectionConfig, CardAsset}, deck::{self, Deck, DeckCard}, units::{self, UnitType, Rarity, SpecialTrait}, registry::{self, UnitRegistry, UnitDefinition}, ruleset::CURRENT_RULESET_VERSION};
 
 declare_id!("AwrQQpL4QssWCUCjqrmZ1uySFGBR32jhhhSwm7A57tcS");
 
 #[program]
 pub mod autochess {
-    use crate::state::{game::{validate_reveal, WinCondition}, entities::Controller};
+    use crate::state::game::{validate_reveal, WinCondition};
 
     use super::*;
 
@@ -33,6 +33,14 @@ pub mod autochess {
 
         game.i_burner = Pubkey::new_from_array(burner_wallet);
 
+        // Snapshot the unit registry if it has been set up. Without it, built in stats are used.
+        let registry_info = ctx.accounts.unit_registry.to_account_info();
+        if registry_info.owner == ctx.program_id && !registry_info.data_is_empty() {
+            let unit_registry: Account<UnitRegistry> = Account::try_from(&registry_info)?;
+            game.unit_definitions = unit_registry.units.clone();
+            game.registry_version = unit_registry.version;
+        }
+
         // Collect sol for the wager
         let ix = anchor_lang::solana_program::system_instruction::transfer(
             &game.initializer,
@@ -64,6 +72,36 @@ pub mod autochess {
         Ok(())
     }
 
+    /// state = 0. Initializer chooses how hands are determined before an opponent joins.
+    /// Draft mode can't be chosen for a multi round game.
+    pub fn configure_hand_mode(ctx: Context<ConfigureGame>, hand_mode: HandMode) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        game.configure_hand_mode(hand_mode)
+    }
+
+    /// state = 0. Initializer turns the game into a multi round game with a shop and persistent boards.
+    /// Draft games can't be made multi round.
+    pub fn configure_multi_round(ctx: Context<ConfigureGame>) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        game.configure_multi_round()
+    }
+
+    /// state = 1. In committed hand mode, each player commits to the merkle root of their hand.
+    /// Must be done before the player's first reveal.
+    pub fn commit_hand(ctx: Context<CommitHand>, hand_root: [u8; 32]) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        game.commit_hand(player_type, hand_root)
+    }
+
+    /// state = 1. In deck mode, each player selects one of their decks for their hand to be drawn from.
+    /// Neither player can reveal until both have selected, and a selection can't be changed afterwards.
+    pub fn select_deck(ctx: Context<SelectDeck>) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        game.select_deck(player_type, &ctx.accounts.deck)
+    }
+
     /// If other player is inactive, claim the wager.
     /// Make sure to set inactivity timers on every step that requries both players to make a transaction before advancing the state.
     pub fn claim_inactivity(ctx: Context<ClaimInactivity>) -> ProgramResult {
@@ -138,6 +176,11 @@ pub mod autochess {
         
         // opposing player will be inactive 60 seconds after the first player's reveal
         let inactivity_timer: i64 = clock.unix_timestamp + 60;
+        // Committed hands and decks must be locked in before any randomness is revealed
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        if !game.hand_source_locked(player_type) {
+            return Err(ErrorCode::RevealError.into());
+        }
         // Validate reveal and set inactivity timer for opponent.
         if invoker_is_initializer && !game.i_has_revealed {
             if !validate_reveal(&game.i_commitment_1.unwrap(), &reveal_1, &secret) {
@@ -182,19 +225,41 @@ pub mod autochess {
             game.o_inactivity_timer = None;
             game.i_inactivity_timer = None;
 
-            // A 1 minute (+ a few seconds) timer is started. Once this timer is up, piece placement is disabled so its safe to reveal.
-            let piece_timer: i64 = clock.unix_timestamp + 90 + 5;
-            game.piece_timer = Some(piece_timer);
+            if game.hand_mode == HandMode::Draft {
+                // Placement starts once the draft is over
+                game.start_draft(clock.unix_timestamp);
+            } else if game.multi_round {
+                // Shop phase of the first round. This also starts the piece timer.
+                game.start_round(clock.unix_timestamp);
+            } else {
+                // A 1 minute (+ a few seconds) timer is started. Once this timer is up, piece placement is disabled so its safe to reveal.
+                let piece_timer: i64 = clock.unix_timestamp + 90 + 5;
+                game.piece_timer = Some(piece_timer);
+            }
         }
         Ok(())
     }
 
+    /// state = 6. Pick a card from the shared draft pool. Players alternate picks, each on a 30 second inactivity timer.
+    /// Once both hands are full, state is changed to 2 and the piece timer is started.
+    pub fn draft_pick(ctx: Context<DraftPick>, pool_index: u8) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        let clock = &ctx.accounts.clock;
+        game.draft_pick(player_type, pool_index, clock.unix_timestamp)
+    }
+
     /// state = 2. Place a piece without revealing its type. Reveal its position in your hand.
     /// When piece timer expires, you cannot place anymore pieces.
     pub fn place_piece_hidden(ctx: Context<PlacePiece>, grid_x: u16, grid_y: u16, hand_position: u8) -> ProgramResult {
         let game = &mut ctx.accounts.game;
         let player_type = game.get_player_type(*ctx.accounts.invoker.key);
 
+        // multi round games place from the shop instead
+        if game.multi_round {
+            return Err(ErrorCode::InvalidGameSettings.into());
+        }
+
         // if placing is disabled, error
         if game.placing_disabled {
             return Err(ErrorCode::TimeError.into());
@@ -227,6 +292,41 @@ pub mod autochess {
         Ok(())
     }
 
+    /// state = 2. Park a piece on the bench, either straight from the hand or off the board.
+    /// Benched pieces don't count toward the piece limit and sit out the battle.
+    pub fn bench_piece_hidden(ctx: Context<PlacePiece>, bench_slot: u8, hand_position: u8) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+
+        // if placing is disabled, error
+        if game.placing_disabled {
+            return Err(ErrorCode::TimeError.into());
+        }
+
+        let placed = game.bench_piece_hidden(player_type, bench_slot, hand_position);
+        if placed == None {
+            return Err(ProgramError::InvalidArgument);
+        }
+        Ok(())
+    }
+
+    /// state = 2. Swap two placed pieces, for example a benched piece with one on the board.
+    pub fn swap_piece_hidden(ctx: Context<PlacePiece>, hand_position_a: u8, hand_position_b: u8) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+
+        // if placing is disabled, error
+        if game.placing_disabled {
+            return Err(ErrorCode::TimeError.into());
+        }
+
+        let swapped = game.swap_piece_hidden(player_type, hand_position_a, hand_position_b);
+        if swapped == None {
+            return Err(ProgramError::InvalidArgument);
+        }
+        Ok(())
+    }
+
     /// state = 2. Remove a placed piece.
     /// When piece timer expires, you cannot remove anymore pieces.
     pub fn remove_piece_hidden(ctx: Context<PlacePiece>, hand_position: u8) -> ProgramResult {
@@ -246,6 +346,119 @@ pub mod autochess {
         Ok(())
     }
 
+    /// state = 2, multi round games. Buy a card from your shop and place it on your saved board.
+    pub fn buy_unit(ctx: Context<PlacePiece>, shop_index: u8, grid_x: u16, grid_y: u16) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        check_shop_open(game, ctx.accounts.clock.unix_timestamp)?;
+        game.buy_unit(player_type, shop_index, grid_x, grid_y)
+    }
+
+    /// state = 2, multi round games. Sell a unit off your saved board.
+    pub fn sell_unit(ctx: Context<PlacePiece>, board_index: u8) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        check_shop_open(game, ctx.accounts.clock.unix_timestamp)?;
+        game.sell_unit(player_type, board_index)
+    }
+
+    /// state = 2, multi round games. Buy a card from your shop onto your bench.
+    pub fn buy_unit_to_bench(ctx: Context<PlacePiece>, shop_index: u8) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        check_shop_open(game, ctx.accounts.clock.unix_timestamp)?;
+        game.buy_unit_to_bench(player_type, shop_index)
+    }
+
+    /// state = 2, multi round games. Move a unit from your saved board to your bench.
+    pub fn bench_unit(ctx: Context<PlacePiece>, board_index: u8) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        check_shop_open(game, ctx.accounts.clock.unix_timestamp)?;
+        game.bench_unit(player_type, board_index)
+    }
+
+    /// state = 2, multi round games. Move a unit from your bench onto your saved board.
+    pub fn field_unit(ctx: Context<PlacePiece>, bench_index: u8, grid_x: u16, grid_y: u16) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        check_shop_open(game, ctx.accounts.clock.unix_timestamp)?;
+        game.field_unit(player_type, bench_index, grid_x, grid_y)
+    }
+
+    /// state = 2, multi round games. Sell a unit off your bench.
+    pub fn sell_bench_unit(ctx: Context<PlacePiece>, bench_index: u8) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        check_shop_open(game, ctx.accounts.clock.unix_timestamp)?;
+        game.sell_bench_unit(player_type, bench_index)
+    }
+
+    /// state = 2, multi round games. Pay gold for a new shop.
+    pub fn reroll_shop(ctx: Context<PlacePiece>) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        check_shop_open(game, ctx.accounts.clock.unix_timestamp)?;
+        game.reroll_shop(player_type)
+    }
+
+    /// state = 2, multi round games. Move a unit on your saved board.
+    pub fn move_unit(ctx: Context<PlacePiece>, board_index: u8, grid_x: u16, grid_y: u16) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        check_shop_open(game, ctx.accounts.clock.unix_timestamp)?;
+        game.move_unit(player_type, board_index, grid_x, grid_y)
+    }
+
+    /// state = 2, multi round games after round 1. Once both players lock in or the piece timer runs out,
+    /// load the saved boards and start the battle. Round 1 starts its battle at the second reveal instead.
+    pub fn start_round_battle(ctx: Context<PlacePiece>) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        if !game.multi_round || game.round <= 1 {
+            return Err(ErrorCode::InvalidGameSettings.into());
+        }
+        if ctx.accounts.clock.unix_timestamp < game.piece_timer.unwrap() && !game.placing_disabled {
+            return Err(ErrorCode::TimeError.into());
+        }
+        game.placing_disabled = true;
+        game.load_boards();
+        Ok(())
+    }
+
+    /// state = 2, multi round games. Commit to a share of the next round's randomness, revealed during the battle.
+    pub fn commit_round(ctx: Context<PlacePiece>, commitment: [u8; 32]) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        game.commit_round(player_type, commitment, ctx.accounts.clock.unix_timestamp)
+    }
+
+    /// state = 3, multi round games. Reveal the share of the next round's randomness committed to in the shop phase.
+    /// Inactivity timer is set for opposing player on a succesful reveal.
+    pub fn reveal_round(ctx: Context<RevealRound>, reveal: [u8; 32], secret: [u8; 32]) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        game.reveal_round(player_type, &reveal, &secret, ctx.accounts.clock.unix_timestamp)
+    }
+
+    /// state = 3, multi round games. Once the battle is decided and both players have revealed their share of the
+    /// next round's randomness, the loser takes damage and the next shop phase starts.
+    /// If a player is out of hp, the game stays in state 3 for the winner to claim victory.
+    pub fn finish_round(ctx: Context<FinishRound>) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        if !game.multi_round {
+            return Err(ErrorCode::InvalidGameSettings.into());
+        }
+        game.finish_round(ctx.accounts.clock.unix_timestamp)
+    }
+
+    /// state = 2. Commit to the hand adjustments (mulligan discards and merges) to be declared at the second reveal.
+    /// The commitment is HandAdjustments::commitment, salted with the secret of the player's second commitment.
+    pub fn commit_adjustments(ctx: Context<PlacePiece>, commitment: [u8; 32]) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        game.commit_adjustments(player_type, commitment, ctx.accounts.clock.unix_timestamp)
+    }
+
     /// state = 2. Player locks in their piece placement with the goal of fast forwarding to the second reveal.
     pub fn lock_in(ctx: Context<LockIn>) -> ProgramResult {
         let game = &mut ctx.accounts.game;
@@ -275,59 +488,97 @@ pub mod autochess {
         let player_type = game.get_player_type(*ctx.accounts.invoker.key);
         let clock = &ctx.accounts.clock;
 
-        // Error if there is still time left, unless placing is disabled (meaning both players have locked in).
-        if ctx.accounts.clock.unix_timestamp < game.piece_timer.unwrap() && !game.placing_disabled {
-            return Err(ErrorCode::TimeError.into());
-        }
-        if !game.placing_disabled {
-            game.placing_disabled = true;
+        if game.hand_mode != HandMode::Random && game.hand_mode != HandMode::Draft {
+            return Err(ErrorCode::InvalidGameSettings.into());
         }
+        game.accept_second_reveal(player_type, &reveal_2, &secret, clock.unix_timestamp)?;
+        game.reveal_hidden_pieces(player_type, &reveal_2, &HandAdjustments::default())?;
+        game.finish_second_reveal();
+        Ok(())
+    }
 
-        // opposing player will be inactive 60 seconds after the first player's reveal
-        let inactivity_timer: i64 = clock.unix_timestamp + 60;
-        if player_type == Controller::Initializer && !game.i_has_revealed {
-            if !validate_reveal(&game.i_commitment_2.unwrap(), &reveal_2, &secret) {
-                return Err(ErrorCode::RevealError.into());
-            }
-            game.i_has_revealed = true;
-            game.i_commitment_2 = None;
+    /// state = 2. Second reveal for a player who adjusted their hand, in random or draft hand mode.
+    /// Mulligan: discard i unlocks the replacement at hand position hand_size + i.
+    /// Merge: three matching hand positions become one unit of the next star level at the first position.
+    /// Adjustments stay hidden until now. They must match the commitment made during placement, and are checked
+    /// against the placed pieces.
+    pub fn reveal_second_adjusted(ctx: Context<RevealSecond>, reveal_2: [u8; 32], secret: [u8; 32], adjustments: HandAdjustments) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        let clock = &ctx.accounts.clock;
 
-            game.o_inactivity_timer = Some(inactivity_timer);
-        } else if player_type == Controller::Opponent && !game.o_has_revealed {
-            if !validate_reveal(&game.o_commitment_2.unwrap(), &reveal_2, &secret) {
-                return Err(ErrorCode::RevealError.into());
-            }
-            game.o_has_revealed = true;
-            game.o_commitment_2 = None;
+        if game.hand_mode != HandMode::Random && game.hand_mode != HandMode::Draft {
+            return Err(ErrorCode::InvalidGameSettings.into());
+        }
+        if !game.validate_adjustments(player_type, &adjustments, &secret) {
+            return Err(ErrorCode::MulliganError.into());
+        }
+        game.accept_second_reveal(player_type, &reveal_2, &secret, clock.unix_timestamp)?;
+        game.reveal_hidden_pieces(player_type, &reveal_2, &adjustments)?;
+        game.finish_second_reveal();
+        Ok(())
+    }
 
-            game.i_inactivity_timer = Some(inactivity_timer);
-        } else {
-            return Err(ErrorCode::RevealError.into());
+    /// state = 2. Second reveal for games with committed hands. Instead of re-deriving the draw, the player
+    /// supplies only the cards they placed along with merkle proofs against their hand root.
+    /// State is advanced to 3 once both are revealed.
+    pub fn reveal_second_committed(ctx: Context<RevealSecond>, reveal_2: [u8; 32], secret: [u8; 32], cards: Vec<CardReveal>) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        let clock = &ctx.accounts.clock;
+
+        if game.hand_mode != HandMode::Committed {
+            return Err(ErrorCode::InvalidGameSettings.into());
         }
-        // Combine reveals (xor)
-        match &mut game.reveal_2 {
-            None => {
-                game.reveal_2 = Some(reveal_2);
-            },
-            Some(stored_reveal) => {
-                stored_reveal.iter_mut()
-                    .zip(reveal_2.iter())
-                    .for_each(|(x1, x2)| *x1 ^= *x2);
-                game.reveal_2 = Some(*stored_reveal);
-            }   
+        game.accept_second_reveal(player_type, &reveal_2, &secret, clock.unix_timestamp)?;
+        game.reveal_committed_pieces(player_type, &cards)?;
+        game.finish_second_reveal();
+        Ok(())
+    }
+
+    /// state = 2. Second reveal for collection hand games. The player supplies the card assets they placed,
+    /// with merkle proofs against their hand root. Asset accounts are passed as remaining accounts and
+    /// must be owned by the player's main wallet.
+    pub fn reveal_second_collection(ctx: Context<RevealSecond>, reveal_2: [u8; 32], secret: [u8; 32], reveals: Vec<AssetReveal>) -> ProgramResult {
+        // Load asset accounts in the order of the reveals
+        let mut assets: Vec<CardAsset> = Vec::new();
+        for reveal in &reveals {
+            let info = ctx.remaining_accounts.iter()
+                .find(|info| *info.key == reveal.asset)
+                .ok_or(ErrorCode::ProofError)?;
+            if info.owner != ctx.program_id {
+                return Err(ErrorCode::OwnershipError.into());
+            }
+            let asset = CardAsset::try_deserialize(&mut &info.data.borrow()[..])?;
+            assets.push(asset);
         }
 
-        game.reveal_hidden_pieces(player_type, &reveal_2);
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        let clock = &ctx.accounts.clock;
 
-        if game.i_has_revealed && game.o_has_revealed {
-            game.state = 3;
-            game.i_has_revealed = false;
-            game.o_has_revealed = false;
+        if game.hand_mode != HandMode::Collection {
+            return Err(ErrorCode::InvalidGameSettings.into());
+        }
+        game.accept_second_reveal(player_type, &reveal_2, &secret, clock.unix_timestamp)?;
+        game.reveal_collection_pieces(player_type, &reveals, &assets)?;
+        game.finish_second_reveal();
+        Ok(())
+    }
 
-            // Any inactivity timers are stopped because both players have revealed
-            game.o_inactivity_timer = None;
-            game.i_inactivity_timer = None;
+    /// state = 2. Second reveal for deck games. The player's hand is drawn by shuffling their selected deck.
+    /// State is advanced to 3 once both are revealed.
+    pub fn reveal_second_deck(ctx: Context<RevealSecondDeck>, reveal_2: [u8; 32], secret: [u8; 32]) -> ProgramResult {
+        let game = &mut ctx.accounts.game;
+        let player_type = game.get_player_type(*ctx.accounts.invoker.key);
+        let clock = &ctx.accounts.clock;
+
+        if game.hand_mode != HandMode::Deck {
+            return Err(ErrorCode::InvalidGameSettings.into());
         }
+        game.accept_second_reveal(player_type, &reveal_2, &secret, clock.unix_timestamp)?;
+        game.reveal_deck_pieces(player_type, &reveal_2, &ctx.accounts.deck)?;
+        game.finish_second_reveal();
         Ok(())
     }
 
@@ -389,6 +640,75 @@ pub mod autochess {
         Ok(())
     }
 
+    /// Create the collection config. Only the program's upgrade authority can, and it becomes the minting authority.
+    pub fn initialize_collection(ctx: Context<InitializeCollection>) -> ProgramResult {
+        let collection = &mut ctx.accounts.collection;
+        collection.authority = *ctx.accounts.authority.key;
+        collection.minted = 0;
+        Ok(())
+    }
+
+    /// Mint a new card asset to a player. Stats are the baseline stats for the unit type and rarity.
+    pub fn mint_card_asset(ctx: Context<MintCardAsset>, owner: Pubkey, unit_type: UnitType, rarity: Rarity, special_trait: Option<SpecialTrait>) -> ProgramResult {
+        let collection = &mut ctx.accounts.collection;
+        let card_asset = &mut ctx.accounts.card_asset;
+        let stats = units::get_baseline_unit_stats(CURRENT_RULESET_VERSION, unit_type, rarity).ok_or(ProgramError::InvalidArgument)?;
+
+        card_asset.id = collection.minted;
+        card_asset.owner = owner;
+        card_asset.card = units::Card {
+            unit_type,
+            stats,
+            rarity,
+            special_trait,
+            star_level: 1,
+        };
+        collection.minted = collection.minted.checked_add(1).ok_or(ProgramError::InvalidArgument)?;
+        Ok(())
+    }
+
+    /// Give a card asset to another wallet
+    pub fn transfer_card_asset(ctx: Context<TransferCardAsset>, new_owner: Pubkey) -> ProgramResult {
+        let card_asset = &mut ctx.accounts.card_asset;
+        card_asset.owner = new_owner;
+        Ok(())
+    }
+
+    /// Register a constructed deck. It must follow the deck construction rules.
+    pub fn register_deck(ctx: Context<RegisterDeck>, _deck_id: String, cards: Vec<DeckCard>) -> ProgramResult {
+        deck::validate_deck(&cards)?;
+        let deck = &mut ctx.accounts.deck;
+        deck.owner = *ctx.accounts.owner.key;
+        deck.cards = cards;
+        Ok(())
+    }
+
+    /// Delete a deck and reclaim its rent
+    pub fn close_deck(_ctx: Context<CloseDeck>) -> ProgramResult {
+        Ok(())
+    }
+
+    /// Create the unit registry, seeded with the built in stats. Only the program's upgrade authority can, and it
+    /// becomes the registry authority.
+    pub fn initialize_unit_registry(ctx: Context<InitializeUnitRegistry>) -> ProgramResult {
+        let unit_registry = &mut ctx.accounts.unit_registry;
+        unit_registry.authority = *ctx.accounts.authority.key;
+        unit_registry.version = 1;
+        unit_registry.units = registry::default_unit_definitions();
+        Ok(())
+    }
+
+    /// Replace the unit definitions in the registry. Games that already snapshotted the registry are unaffected.
+    pub fn update_unit_registry(ctx: Context<UpdateUnitRegistry>, units: Vec<UnitDefinition>) -> ProgramResult {
+        if !registry::validate_definitions(&units) {
+            return Err(ErrorCode::RegistryError.into());
+        }
+        let unit_registry = &mut ctx.accounts.unit_registry;
+        unit_registry.units = units;
+        unit_registry.version = unit_registry.version.checked_add(1).ok_or(ProgramError::InvalidArgument)?;
+        Ok(())
+    }
+
     /// drain burner wallet and send funds to a specified main wallet
     pub fn drain_burner(ctx: Context<DrainBurner>) -> ProgramResult {
         let burner = &ctx.accounts.burner;
@@ -419,12 +739,18 @@ pub struct CreateGame<'info> {
         constraint = game_id.len() < 30,
         seeds = [game_id.as_bytes(), b"Game"],
         bump,
-        space = 700,
+        space = state::game::GAME_ACCOUNT_SPACE,
         payer = initializer, owner = *program_id,
     )]
     game: Account<'info, Game>,
     #[account(mut)]
     initializer: Signer<'info>,
+    /// Snapshotted into the game if it has been initialized
+    #[account(
+        seeds = [b"UnitRegistry"],
+        bump,
+    )]
+    unit_registry: UncheckedAccount<'info>,
     system_program: Program<'info, System>,
 }
 
@@ -441,6 +767,28 @@ pub struct CancelGame<'info> {
     initializer: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct ConfigureGame<'info> {
+    #[account(
+        mut,
+        constraint = game.state == 0,
+        constraint = game.initializer == *initializer.key,
+    )]
+    game: Account<'info, Game>,
+    initializer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CommitHand<'info> {
+    #[account(
+        mut,
+        constraint = game.state == 1,
+        constraint = game.i_burner == *invoker.key || game.o_burner == *invoker.key,
+    )]
+    game: Account<'info, Game>,
+    invoker: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct ClaimInactivity<'info> {
     #[account(
@@ -483,6 +831,18 @@ pub struct RevealFirst<'info> {
     clock: Sysvar<'info, Clock>,
 }
 
+#[derive(Accounts)]
+pub struct DraftPick<'info> {
+    #[account(
+        mut,
+        constraint = game.state == 6,
+        constraint = game.i_burner == *invoker.key || game.o_burner == *invoker.key,
+    )]
+    game: Account<'info, Game>,
+    invoker: Signer<'info>,
+    clock: Sysvar<'info, Clock>,
+}
+
 #[derive(Accounts)]
 pub struct PlacePiece<'info> {
     #[account(
@@ -519,6 +879,47 @@ pub struct RevealSecond<'info> {
     clock: Sysvar<'info, Clock>
 }
 
+#[derive(Accounts)]
+pub struct RevealSecondDeck<'info> {
+    #[account(
+        mut,
+        constraint = game.state == 2,
+        constraint = game.i_burner == *invoker.key || game.o_burner == *invoker.key,
+    )]
+    game: Account<'info, Game>,
+    deck: Account<'info, Deck>,
+    invoker: Signer<'info>,
+    clock: Sysvar<'info, Clock>
+}
+
+#[derive(Accounts)]
+pub struct SelectDeck<'info> {
+    #[account(
+        mut,
+        constraint = game.state == 1,
+        constraint = game.i_burner == *invoker.key || game.o_burner == *invoker.key,
+    )]
+    game: Account<'info, Game>,
+    #[account(
+        constraint = (game.i_burner == *invoker.key && deck.owner == game.initializer)
+            || (game.o_burner == *invoker.key && deck.owner == game.opponent),
+    )]
+    deck: Account<'info, Deck>,
+    invoker: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RevealRound<'info> {
+    #[account(
+        mut,
+        constraint = game.state == 3,
+        constraint = game.i_burner == *invoker.key || game.o_burner == *invoker.key,
+    )]
+    game: Account<'info, Game>,
+    invoker: Signer<'info>,
+    clock: Sysvar<'info, Clock>,
+}
+
 #[derive(Accounts)]
 pub struct CrankGame<'info> {
     #[account(
@@ -530,6 +931,18 @@ pub struct CrankGame<'info> {
     invoker: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct FinishRound<'info> {
+    #[account(
+        mut,
+        constraint = game.state == 3,
+        constraint = game.i_burner == *invoker.key || game.o_burner == *invoker.key,
+    )]
+    game: Account<'info, Game>,
+    invoker: Signer<'info>,
+    clock: Sysvar<'info, Clock>,
+}
+
 #[derive(Accounts)]
 pub struct ClaimVictory<'info> {
     #[account(
@@ -559,6 +972,128 @@ pub struct DrainBurner<'info> {
 }
 
 
+#[derive(Accounts)]
+pub struct InitializeCollection<'info> {
+    #[account(
+        init,
+        seeds = [b"Collection"],
+        bump,
+        space = 100,
+        payer = authority, owner = *program_id,
+    )]
+    collection: Account<'info, CollectionConfig>,
+    #[account(mut)]
+    authority: Signer<'info>,
+    #[account(constraint = program.programdata_address() == Some(program_data.key()))]
+    program: Program<'info, crate::program::Autochess>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(*authority.key))]
+    program_data: Account<'info, ProgramData>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MintCardAsset<'info> {
+    #[account(
+        mut,
+        seeds = [b"Collection"],
+        bump,
+        constraint = collection.authority == *authority.key,
+    )]
+    collection: Account<'info, CollectionConfig>,
+    #[account(
+        init,
+        seeds = [b"CardAsset", collection.minted.to_le_bytes().as_ref()],
+        bump,
+        space = 100,
+        payer = authority, owner = *program_id,
+    )]
+    card_asset: Account<'info, CardAsset>,
+    #[account(mut)]
+    authority: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TransferCardAsset<'info> {
+    #[account(
+        mut,
+        constraint = card_asset.owner == *owner.key,
+    )]
+    card_asset: Account<'info, CardAsset>,
+    owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(deck_id: String)]
+pub struct RegisterDeck<'info> {
+    #[account(
+        init,
+        constraint = deck_id.len() < 30,
+        seeds = [owner.key.as_ref(), deck_id.as_bytes(), b"Deck"],
+        bump,
+        space = 300,
+        payer = owner, owner = *program_id,
+    )]
+    deck: Account<'info, Deck>,
+    #[account(mut)]
+    owner: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseDeck<'info> {
+    #[account(
+        mut,
+        constraint = deck.owner == *owner.key,
+        close = owner,
+    )]
+    deck: Account<'info, Deck>,
+    #[account(mut)]
+    owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeUnitRegistry<'info> {
+    #[account(
+        init,
+        seeds = [b"UnitRegistry"],
+        bump,
+        space = 600,
+        payer = authority, owner = *program_id,
+    )]
+    unit_registry: Account<'info, UnitRegistry>,
+    #[account(mut)]
+    authority: Signer<'info>,
+    #[account(constraint = program.programdata_address() == Some(program_data.key()))]
+    program: Program<'info, crate::program::Autochess>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(*authority.key))]
+    program_data: Account<'info, ProgramData>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateUnitRegistry<'info> {
+    #[account(
+        mut,
+        seeds = [b"UnitRegistry"],
+        bump,
+        constraint = unit_registry.authority == *authority.key,
+    )]
+    unit_registry: Account<'info, UnitRegistry>,
+    authority: Signer<'info>,
+}
+
+/// Shop actions are only allowed in the shop phase of a multi round game, before the piece timer runs out
+fn check_shop_open(game: &Game, now: i64) -> ProgramResult {
+    if !game.multi_round {
+        return Err(ErrorCode::InvalidGameSettings.into());
+    }
+    if game.placing_disabled || now > game.piece_timer.unwrap() {
+        return Err(ErrorCode::TimeError.into());
+    }
+    Ok(())
+}
+
 #[error]
 pub enum ErrorCode {
     #[msg("Time Limit Exceeded")]
@@ -571,4 +1106,20 @@ pub enum ErrorCode {
     LockInError,
     #[msg("Invalid game settings")]
     InvalidGameSettings,
+    #[msg("Revealed card does not match committed hand")]
+    ProofError,
+    #[msg("Card asset is not owned by the player")]
+    OwnershipError,
+    #[msg("Deck does not follow construction rules")]
+    InvalidDeck,
+    #[msg("Invalid draft pick")]
+    DraftError,
+    #[msg("Hand adjustments do not match the commitment or placed pieces")]
+    MulliganError,
+    #[msg("Not enough gold")]
+    GoldError,
+    #[msg("Invalid unit merge")]
+    MergeError,
+    #[msg("Unit definitions must cover every unit type and rarity once")]
+    RegistryError,
 }
\ No newline at end of file
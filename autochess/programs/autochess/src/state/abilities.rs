@@ -0,0 +1,116 @@
+use anchor_lang::prelude::*;
+use super::{units::{UnitType, DamageType}, entities::{Entity, Entities, EntityState, EntityBuff}, actions::{Action, Actions}, utils::Location};
+
+/// Mana needed to cast an ability
+pub const MAX_MANA: u16 = 100;
+/// Mana gained each time an auto attack lands
+pub const MANA_PER_ATTACK: u16 = 10;
+/// Mana gained each time a unit takes damage
+pub const MANA_PER_HIT: u16 = 5;
+/// Mana gained for a killing blow
+pub const MANA_PER_KILL: u16 = 20;
+
+/// Signature ability of a unit type, cast at full mana
+#[derive(Debug, PartialEq, AnchorDeserialize, AnchorSerialize, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum Ability {
+    /// Bear: nearby enemies switch target to the bear, and it shields itself
+    Taunt,
+    /// Wolf: a strong bite that makes the target bleed over time, healing the wolf for part of the hit
+    Bleed,
+    /// Bull: charge into the target, damaging and stunning it, with splash damage around it
+    Charge,
+    /// Hawk: damage the target and every enemy around it
+    Volley,
+}
+
+const TAUNT_RADIUS: u16 = 250;
+/// Percent of max health gained as shield
+const TAUNT_SHIELD_PERCENT: u32 = 20;
+const BLEED_TICKS: u16 = 10;
+const CHARGE_SPLASH_RADIUS: u16 = 100;
+const CHARGE_STUN_TICKS: u16 = 5;
+const VOLLEY_RADIUS: u16 = 120;
+
+pub fn get_signature_ability(unit_type: UnitType) -> Option<Ability> {
+    match unit_type {
+        UnitType::Wolf => Some(Ability::Bleed),
+        UnitType::Bear => Some(Ability::Taunt),
+        UnitType::Bull => Some(Ability::Charge),
+        UnitType::Hawk => Some(Ability::Volley),
+        UnitType::Hidden{..} => None,
+    }
+}
+
+impl Ability {
+    /// Ticks into the Ability state at which the effect happens (cast_on) and the unit goes back to Idle (release_on)
+    pub fn timing(&self) -> (u16, u16) {
+        match self {
+            Ability::Taunt => (2, 4),
+            Ability::Bleed => (2, 3),
+            Ability::Charge => (3, 6),
+            Ability::Volley => (3, 5),
+        }
+    }
+
+    /// Whether casting now would do anything. Mana is kept until there is something to cast on.
+    pub fn can_cast(&self, caster: &Entity, all_entities: &Entities) -> bool {
+        match self {
+            Ability::Taunt => all_entities.find_closest_entity_in_range(&caster.position, TAUNT_RADIUS, caster.get_enemy()).is_some(),
+            Ability::Bleed | Ability::Charge | Ability::Volley => get_live_target(caster, all_entities)
+                .map_or(false, |target| caster.position.distance(&target.position) <= caster.stats.as_ref().unwrap().attack_range),
+        }
+    }
+
+    /// Queue the effects of the ability. Targeted abilities fizzle if the target died during the cast.
+    pub fn cast(&self, caster: &Entity, all_entities: &Entities, actions: &mut Actions) {
+        let stats = caster.stats.as_ref().unwrap();
+        match self {
+            Ability::Taunt => {
+                for enemy in all_entities.find_all_entities(&caster.position, TAUNT_RADIUS, caster.get_enemy()) {
+                    actions.add(enemy.id, Action::Target { target_id: Some(caster.id) });
+                }
+                let shield = (stats.starting_health as u32 * TAUNT_SHIELD_PERCENT / 100) as u16;
+                // lasts until it is broken. From BUFF_MERGING_VERSION a recast refreshes it instead of adding another.
+                actions.add(caster.id, Action::ApplyBuff { buff: EntityBuff::Shield { duration: u16::MAX, amount: shield } });
+            },
+            Ability::Bleed => {
+                if let Some(target) = get_live_target(caster, all_entities) {
+                    let damage = stats.attack_damage * 2;
+                    actions.add(target.id, Action::Damage { amount: damage, source: Some(caster.id), crit: false, damage_type: DamageType::Magic });
+                    actions.add(target.id, Action::ApplyBuff { buff: EntityBuff::Poison { duration: BLEED_TICKS, damage_per_tick: (stats.attack_damage / 5).max(1) } });
+                    actions.add(caster.id, Action::Heal { amount: damage / 2 });
+                }
+            },
+            Ability::Charge => {
+                if let Some(target) = get_live_target(caster, all_entities) {
+                    // stop just short of the target
+                    let distance = caster.position.distance(&target.position);
+                    if distance > 50 {
+                        let to: Location = caster.position.move_towards(&target.position, distance, distance - 50);
+                        actions.add(caster.id, Action::Move { to });
+                    }
+                    for enemy in all_entities.find_all_entities(&target.position, CHARGE_SPLASH_RADIUS, caster.get_enemy()) {
+                        if enemy.id != target.id {
+                            actions.add(enemy.id, Action::Damage { amount: stats.attack_damage, source: Some(caster.id), crit: false, damage_type: DamageType::Magic });
+                        }
+                    }
+                    actions.add(target.id, Action::Damage { amount: stats.attack_damage * 2, source: Some(caster.id), crit: false, damage_type: DamageType::Magic });
+                    actions.add(target.id, Action::ApplyBuff { buff: EntityBuff::Stun { duration: CHARGE_STUN_TICKS } });
+                }
+            },
+            Ability::Volley => {
+                if let Some(target) = get_live_target(caster, all_entities) {
+                    for enemy in all_entities.find_all_entities(&target.position, VOLLEY_RADIUS, caster.get_enemy()) {
+                        actions.add(enemy.id, Action::Damage { amount: stats.attack_damage, source: Some(caster.id), crit: false, damage_type: DamageType::Magic });
+                    }
+                }
+            },
+        }
+    }
+}
+
+fn get_live_target<'a>(caster: &Entity, all_entities: &'a Entities) -> Option<&'a Entity> {
+    caster.target
+        .and_then(|id| all_entities.get_by_id(id))
+        .filter(|target| target.state != EntityState::Dead)
+}
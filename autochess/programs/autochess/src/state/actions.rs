@@ -1,6 +1,6 @@
 use std::{collections::BTreeMap};
 
-use super::{entities::{EntityState}, utils::Location};
+use super::{entities::{EntityState, EntityBuff}, utils::Location, units::DamageType};
 
 pub struct Actions {
     pub all: BTreeMap<u16, Vec<Action>>, //map from id to a vector of actions
@@ -28,6 +28,27 @@ impl Actions {
     }
 }
 
+/// A basic attack that landed this tick. Collected in Game::step to raise trait events.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Hit {
+    pub attacker_id: u16,
+    pub target_id: u16,
+    pub damage: u16,
+}
+
+/// Phases queued actions are applied in from PHASES_VERSION, in this order. Every entity goes through a phase before
+/// any entity starts the next one, so the outcome of a tick doesn't depend on the order entities were created in.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum TickPhase {
+    /// Positions and targets
+    Movement,
+    /// Every hit lands before any healing, so a unit brought to 0 health this tick can't be healed back up
+    Damage,
+    /// Buffs count down before new ones go on, mana is spent before it's gained, and state changes come last so
+    /// a stun applied this tick always interrupts
+    State,
+}
+
 /// When a unit needs to modify another unit or itself, add an Apply into a vector and it will be modified at the end of the game loop
 pub enum Action {
     EntityStateChange {
@@ -41,5 +62,41 @@ pub enum Action {
     },
     Damage {
         amount: u16,
+        /// Entity the damage came from, if any. Damage over time has no source.
+        source: Option<u16>,
+        crit: bool,
+        damage_type: DamageType,
+    },
+    /// Restore health, up to the unit's max health. See Entity::heal for overheal.
+    Heal {
+        amount: u16,
+    },
+    /// Add a timed effect. See Entity::apply_buff for how it combines with existing ones.
+    ApplyBuff {
+        buff: EntityBuff,
     },
+    GainMana {
+        amount: u16,
+    },
+    /// Spend all mana on casting an ability
+    ResetMana,
+    /// Count down every buff by one tick
+    TickBuffs,
+}
+
+impl Action {
+    /// Phase the action is applied in, and its rank within the phase. Actions of the same rank on one entity keep
+    /// the order they were queued in.
+    pub fn phase(&self) -> (TickPhase, u8) {
+        match self {
+            Action::Move{..} | Action::Target{..} => (TickPhase::Movement, 0),
+            Action::Damage{..} => (TickPhase::Damage, 0),
+            Action::Heal{..} => (TickPhase::Damage, 1),
+            Action::TickBuffs => (TickPhase::State, 0),
+            Action::ApplyBuff{..} => (TickPhase::State, 1),
+            Action::ResetMana => (TickPhase::State, 2),
+            Action::GainMana{..} => (TickPhase::State, 3),
+            Action::EntityStateChange{..} => (TickPhase::State, 4),
+        }
+    }
 }
\ No newline at end of file
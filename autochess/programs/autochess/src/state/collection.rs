@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+use super::units::Card;
+
+/// Global settings for the card collection. PDA with seed "Collection".
+#[account]
+#[derive(Default)]
+pub struct CollectionConfig {
+    /// Only this key can mint new card assets
+    pub authority: Pubkey,
+    /// Number of card assets minted so far. Used as the id of the next asset.
+    pub minted: u64,
+}
+
+/// A card owned by a player. Usable in collection hand mode games.
+/// PDA with seeds "CardAsset" and the asset id.
+#[account]
+pub struct CardAsset {
+    pub id: u64,
+    pub owner: Pubkey,
+    pub card: Card,
+}
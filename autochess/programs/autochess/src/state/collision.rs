@@ -0,0 +1,146 @@
+use super::{entities::{Entities, EntityState}, utils::Location, fixed, ruleset};
+
+/// Closest two living units may stand to each other
+pub const MIN_SEPARATION: u16 = 40;
+/// Edge of the 800 x 800 board
+const BOARD_SIZE: i64 = 800;
+/// Separation is repeated a few times so pushes that cause new overlaps settle within the tick
+const SEPARATION_PASSES: usize = 2;
+
+/// Push apart living units that are closer than MIN_SEPARATION. Airborne units fly over everyone and are left alone.
+/// From PHASES_VERSION both units in a pair move apart by half the overlap, all from the positions at the start of
+/// the pass. Before that, pairs were resolved in id order and the unit with the higher id was the one moved.
+pub fn separate(entities: &mut Entities, ruleset_version: u16) {
+    let mut order: Vec<usize> = (0..entities.all.len())
+        .filter(|i| {
+            let entity = &entities.all[*i];
+            !entity.is_benched() && !matches!(entity.state, EntityState::Dead | EntityState::Airborne{..})
+        })
+        .collect();
+    order.sort_by_key(|i| entities.all[*i].id);
+
+    if ruleset_version >= ruleset::PHASES_VERSION {
+        for _ in 0..SEPARATION_PASSES {
+            separate_evenly(entities, &order);
+        }
+        return;
+    }
+    for _ in 0..SEPARATION_PASSES {
+        for (n, a) in order.iter().enumerate() {
+            for b in &order[n + 1..] {
+                let from = entities.all[*a].position;
+                let position = entities.all[*b].position;
+                let distance = from.distance(&position);
+                if distance < MIN_SEPARATION {
+                    entities.all[*b].position = push_away(&from, &position, distance, entities.all[*b].id);
+                }
+            }
+        }
+    }
+}
+
+/// One pass of pushing both units of every overlapping pair apart. Pushes are summed from the positions at the start
+/// of the pass, so the order pairs are visited in doesn't matter.
+fn separate_evenly(entities: &mut Entities, order: &Vec<usize>) {
+    let mut pushes = vec![(0i64, 0i64); order.len()];
+    for n in 0..order.len() {
+        for m in n + 1..order.len() {
+            let (a, b) = (&entities.all[order[n]], &entities.all[order[m]]);
+            let distance = a.position.distance(&b.position);
+            if distance >= MIN_SEPARATION {
+                continue;
+            }
+            let (dx, dy, distance) = if distance == 0 {
+                // stacked exactly, so pick a direction from the pair
+                direction(a.id ^ b.id)
+            } else {
+                (b.position.x as i64 - a.position.x as i64, b.position.y as i64 - a.position.y as i64, distance as i64)
+            };
+            let overlap = (MIN_SEPARATION as i64 - distance).max(1);
+            let shift = (fixed::mul_div_floor(dx, overlap, 2 * distance), fixed::mul_div_floor(dy, overlap, 2 * distance));
+            pushes[n] = (pushes[n].0 - shift.0, pushes[n].1 - shift.1);
+            pushes[m] = (pushes[m].0 + shift.0, pushes[m].1 + shift.1);
+        }
+    }
+    for (n, (dx, dy)) in pushes.into_iter().enumerate() {
+        let position = &mut entities.all[order[n]].position;
+        position.x = (position.x as i64 + dx).clamp(0, BOARD_SIZE) as u16;
+        position.y = (position.y as i64 + dy).clamp(0, BOARD_SIZE) as u16;
+    }
+}
+
+/// Unit step in one of four directions, as (dx, dy, length)
+fn direction(seed: u16) -> (i64, i64, i64) {
+    match seed % 4 {
+        0 => (1, 0, 1),
+        1 => (0, 1, 1),
+        2 => (-1, 0, 1),
+        _ => (0, -1, 1),
+    }
+}
+
+/// Position at exactly MIN_SEPARATION from `from`, in the direction of `position`
+fn push_away(from: &Location, position: &Location, distance: u16, id: u16) -> Location {
+    let (dx, dy, distance) = if distance == 0 {
+        // stacked exactly, so pick a direction from the id
+        direction(id)
+    } else {
+        (position.x as i64 - from.x as i64, position.y as i64 - from.y as i64, distance as i64)
+    };
+    let push = |coordinate: u16, delta: i64| {
+        let moved = coordinate as i64 + fixed::mul_div_floor(delta, MIN_SEPARATION as i64, distance);
+        moved.clamp(0, BOARD_SIZE) as u16
+    };
+    Location {
+        x: push(from.x, dx),
+        y: push(from.y, dy),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{entities::Controller, units::{self, UnitType}, ruleset::CURRENT_RULESET_VERSION};
+
+    #[test]
+    fn stacked_units_are_pushed_apart() {
+        let card = units::test_card(UnitType::Bear, None);
+        let mut entities = Entities::default();
+        for _ in 0..3 {
+            entities.create(Controller::Initializer, 400, 400, card.clone());
+        }
+        entities.create(Controller::Opponent, 410, 400, card);
+        separate(&mut entities, ruleset::COLLISION_VERSION);
+
+        // the lowest id never moves
+        assert_eq!(entities.all[0].position, Location { x: 400, y: 400 });
+        for a in 0..entities.all.len() {
+            for b in a + 1..entities.all.len() {
+                let distance = entities.all[a].position.distance(&entities.all[b].position);
+                assert!(distance >= MIN_SEPARATION - 1, "{} and {} are {} apart", a, b, distance);
+            }
+        }
+    }
+
+    #[test]
+    fn both_units_give_way_from_phases_version() {
+        let separated = |opponent_first: bool| {
+            let mut entities = Entities::default();
+            let mut units = vec![(Controller::Initializer, 400), (Controller::Opponent, 420)];
+            if opponent_first {
+                units.reverse();
+            }
+            for (owner, y) in units {
+                entities.create(owner, 400, y, units::test_card(UnitType::Bear, None));
+            }
+            separate(&mut entities, CURRENT_RULESET_VERSION);
+            let position = |owner| entities.all.iter().find(|entity| entity.owner == owner).unwrap().position;
+            (position(Controller::Initializer), position(Controller::Opponent))
+        };
+        let (initializer, opponent) = separated(false);
+        assert_eq!(initializer, Location { x: 400, y: 390 });
+        assert_eq!(opponent, Location { x: 400, y: 430 });
+        // creating the opponent's piece first changes nothing
+        assert_eq!(separated(true), (initializer, opponent));
+    }
+}
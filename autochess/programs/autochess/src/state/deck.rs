@@ -0,0 +1,110 @@
+use anchor_lang::{solana_program::hash::hash, prelude::*};
+use super::{units::{Card, UnitType, Rarity, SpecialTrait}, ruleset::Ruleset};
+use crate::ErrorCode;
+
+pub const MIN_DECK_SIZE: usize = 12;
+pub const MAX_DECK_SIZE: usize = 30;
+/// No more than this many cards of a single unit type
+pub const MAX_COPIES_PER_UNIT_TYPE: usize = 12;
+/// Total rarity cost a deck may spend. See rarity_cost.
+pub const RARITY_BUDGET: u16 = 40;
+
+/// A constructed deck registered by a player. PDA with seeds owner, deck id, "Deck".
+#[account]
+#[derive(Default)]
+pub struct Deck {
+    pub owner: Pubkey,
+    pub cards: Vec<DeckCard>,
+}
+
+impl Deck {
+    /// Hash of the deck contents. Stored in the game when a deck is selected so later edits can't change the draw.
+    pub fn content_hash(&self) -> [u8; 32] {
+        hash(&self.cards.try_to_vec().unwrap()).to_bytes()
+    }
+}
+
+/// A card in a deck. Stats aren't stored, they are always the baseline stats for the type and rarity.
+#[derive(Debug, PartialEq, AnchorDeserialize, AnchorSerialize, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct DeckCard {
+    pub unit_type: UnitType,
+    pub rarity: Rarity,
+    pub special_trait: Option<SpecialTrait>,
+}
+
+impl DeckCard {
+    pub fn to_card(&self, ruleset: &Ruleset) -> Option<Card> {
+        Some(Card {
+            unit_type: self.unit_type,
+            stats: ruleset.get_unit_stats(self.unit_type, self.rarity, 1)?,
+            rarity: self.rarity,
+            special_trait: self.special_trait,
+            star_level: 1,
+        })
+    }
+}
+
+/// Cost of a card against the rarity budget
+pub fn rarity_cost(card: &DeckCard) -> u16 {
+    let rarity_cost = match card.rarity {
+        Rarity::Common => 0,
+        Rarity::Uncommon => 1,
+        Rarity::Rare => 2,
+        Rarity::Epic => 4,
+        Rarity::Legendary => 8,
+        Rarity::Mythic => 16,
+    };
+    let trait_cost = if card.special_trait.is_some() { 2 } else { 0 };
+    rarity_cost + trait_cost
+}
+
+/// Check a deck against the construction rules:
+///  - between MIN_DECK_SIZE and MAX_DECK_SIZE cards
+///  - at most MAX_COPIES_PER_UNIT_TYPE of any unit type
+///  - total rarity cost within RARITY_BUDGET
+pub fn validate_deck(cards: &Vec<DeckCard>) -> ProgramResult {
+    if cards.len() < MIN_DECK_SIZE || cards.len() > MAX_DECK_SIZE {
+        return Err(ErrorCode::InvalidDeck.into());
+    }
+    let mut budget_used: u16 = 0;
+    for card in cards {
+        // hidden units can't be built into a deck
+        if card.to_card(&Ruleset::current()) == None {
+            return Err(ErrorCode::InvalidDeck.into());
+        }
+        let copies = cards.iter().filter(|other| other.unit_type == card.unit_type).count();
+        if copies > MAX_COPIES_PER_UNIT_TYPE {
+            return Err(ErrorCode::InvalidDeck.into());
+        }
+        budget_used += rarity_cost(card);
+    }
+    if budget_used > RARITY_BUDGET {
+        return Err(ErrorCode::InvalidDeck.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deck_of(n: usize, unit_type: UnitType, rarity: Rarity) -> Vec<DeckCard> {
+        vec![DeckCard { unit_type, rarity, special_trait: None }; n]
+    }
+
+    #[test]
+    fn validate_deck_rules() {
+        let mut cards = deck_of(10, UnitType::Wolf, Rarity::Common);
+        cards.extend(deck_of(10, UnitType::Bear, Rarity::Rare));
+        assert!(validate_deck(&cards).is_ok());
+
+        // too small
+        assert!(validate_deck(&deck_of(5, UnitType::Wolf, Rarity::Common)).is_err());
+        // too many copies of one type
+        assert!(validate_deck(&deck_of(20, UnitType::Wolf, Rarity::Common)).is_err());
+        // over budget
+        let mut expensive = deck_of(10, UnitType::Wolf, Rarity::Common);
+        expensive.extend(deck_of(3, UnitType::Bull, Rarity::Mythic));
+        assert!(validate_deck(&expensive).is_err());
+    }
+}
@@ -1,8 +1,15 @@
 use anchor_lang::{prelude::*, solana_program::log::sol_log_compute_units};
-use super::{utils::Location, actions::{Action, Actions}, units::{UnitType, UnitStats, Card, Rarity, SpecialTrait}};
+use super::{utils::Location, actions::{Action, Actions}, units::{UnitType, UnitStats, Card, Rarity, SpecialTrait, DamageType}, abilities::{self, MAX_MANA, MANA_PER_HIT}, ruleset, pathfinding, targeting::{self, TargetingPolicy}};
 
 use serde;
 
+/// Share of healing past max health that turns into a shield, in percent, when overheal is on
+const OVERHEAL_SHIELD_PERCENT: u32 = 50;
+const OVERHEAL_SHIELD_TICKS: u16 = 30;
+/// Most buffs a unit can hold. Only reachable before BUFF_MERGING_VERSION, when buffs of a kind stack.
+/// Keeps the game account inside its space however long a battle runs.
+pub const MAX_BUFFS: usize = 8;
+
 #[derive(Debug, Default, AnchorDeserialize, AnchorSerialize, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Entities {
     pub all: Vec<Entity>,
@@ -27,14 +34,21 @@ impl Entities {
             owner: player,
             target: None,
             speed_multiplier: 100,
+            attack_speed_multiplier: 100,
+            base_attack_speed_multiplier: 100,
             position: Location{x, y},
             health: stats.starting_health,
+            max_health: stats.starting_health,
+            lifesteal: 0,
             unit_type,
             state: EntityState::Idle,
-            // buffs: Vec::new(),
+            buffs: Vec::new(),
             stats: Some(stats.clone()),
             rarity: Some(rarity),
             special_trait,
+            star_level: card.star_level,
+            bench_slot: None,
+            mana: 0,
         });
         return id;
     }
@@ -52,14 +66,21 @@ impl Entities {
             owner: player,
             target: None,
             speed_multiplier: 100,
+            attack_speed_multiplier: 100,
+            base_attack_speed_multiplier: 100,
             position: Location{x, y},
             health: 0,
+            max_health: 0,
+            lifesteal: 0,
             unit_type: UnitType::Hidden{hand_position},
             state: EntityState::Idle,
-            // buffs: Vec::new(),
+            buffs: Vec::new(),
             stats: None,
             rarity: None,
             special_trait: None,
+            star_level: 0,
+            bench_slot: None,
+            mana: 0,
         });
         self.counter += 1;
         return id;
@@ -78,9 +99,11 @@ impl Entities {
 
                         entity.unit_type = unit_type;
                         entity.health = stats.starting_health;
+                        entity.max_health = stats.starting_health;
                         entity.stats = Some(stats.clone());
                         entity.rarity = Some(rarity);
                         entity.special_trait = special_trait;
+                        entity.star_level = card.star_level;
                     },
                     _ => {
                         panic!("shouldn't reach here bc all pieces should be hidden");
@@ -90,6 +113,28 @@ impl Entities {
         }
     }
 
+    /// Reveal the single hidden piece placed from a given hand position. Returns the id of the revealed piece.
+    pub fn reveal_hidden_at(&mut self, player: Controller, hand_position: u8, card: &Card) -> Option<u16> {
+        for entity in &mut self.all {
+            if entity.owner == player && entity.unit_type == (UnitType::Hidden{hand_position}) {
+                entity.unit_type = card.unit_type;
+                entity.health = card.stats.starting_health;
+                entity.max_health = card.stats.starting_health;
+                entity.stats = Some(card.stats.clone());
+                entity.rarity = Some(card.rarity);
+                entity.special_trait = card.special_trait;
+                entity.star_level = card.star_level;
+                return Some(entity.id);
+            }
+        }
+        None
+    }
+
+    /// Check whether a player still has any unrevealed pieces on the board
+    pub fn has_hidden(&self, player: Controller) -> bool {
+        self.all.iter().any(|entity| entity.owner == player && matches!(entity.unit_type, UnitType::Hidden{..}))
+    }
+
     pub fn get_index_by_id(&self, id: u16) -> Option<usize> {
         for (i, entity) in &mut self.all.iter().enumerate() {
             if entity.id == id {
@@ -116,22 +161,35 @@ impl Entities {
         None
     }
 
+    /// Count living pieces on the board for a controller. Benched pieces are not counted.
     pub fn count_for_controller(&self, controller: Controller) -> u8 {
         let mut count: u8 = 0;
         for entity in &self.all {
-            if entity.owner == controller {
+            if entity.owner == controller && !entity.is_benched() && entity.state != EntityState::Dead {
                 count += 1;
             }
         }
         count
     }
 
-    /// Find closest entity to a point. With a given owner and within a given range. If no such units exist return none
+    /// Count a controller's pieces that died in battle
+    pub fn count_lost(&self, controller: Controller) -> u8 {
+        self.all.iter()
+            .filter(|entity| entity.owner == controller && !entity.is_benched() && entity.state == EntityState::Dead)
+            .count() as u8
+    }
+
+    /// Get the piece a controller has parked in a bench slot
+    pub fn get_benched(&self, controller: Controller, bench_slot: u8) -> Option<&Entity> {
+        self.all.iter().find(|entity| entity.owner == controller && entity.bench_slot == Some(bench_slot))
+    }
+
+    /// Find closest living entity to a point. With a given owner and within a given range. If no such units exist return none
     pub fn find_closest_entity_in_range(&self, position: &Location, range: u16, owner: Controller ) -> Option<EntityResult> {
         let mut min_distance: u16 = u16::MAX;
         let mut closest_enemy: Option<EntityResult> = None;
         for other in &self.all {
-            if other.owner == owner {
+            if other.owner == owner && !other.is_benched() && !other.is_untargetable() && other.state != EntityState::Dead {
                 let distance = position.distance(&other.position);
                 if distance < min_distance && distance <= range {
                     min_distance = distance;
@@ -148,11 +206,11 @@ impl Entities {
     pub fn find_closest_entity(&self, position: &Location, owner: Controller) -> Option<EntityResult> {
         self.find_closest_entity_in_range(&position, u16::MAX, owner)
     }
-    /// Get all entities in a range from a given point
+    /// Get all living entities in a range from a given point
     pub fn find_all_entities(&self, position: &Location, range: u16, owner: Controller) -> Vec<&Entity> {
         let mut entities: Vec<&Entity> = Vec::new();
         for other in &self.all {
-            if other.owner == owner {
+            if other.owner == owner && !other.is_benched() && other.state != EntityState::Dead {
                 let distance = position.distance(&other.position);
                 if distance <= range {
                     entities.push(other);
@@ -171,10 +229,18 @@ pub struct Entity {
     /// id of the focused target
     pub target: Option<u16>,
 
-    /// Speed multiplier where 100 = 1x
+    /// Movement speed multiplier where 100 = 1x, from slows. See update_multipliers.
     pub speed_multiplier: u16,
+    /// Attack speed multiplier where 100 = 1x. Base multiplier combined with buffs.
+    pub attack_speed_multiplier: u16,
+    /// Attack speed multiplier set at battle start from rarity and synergies
+    pub base_attack_speed_multiplier: u16,
     pub position: Location, // 800 x 800
     pub health: u16,
+    /// Health cap: starting health plus max health buffs. Kept up to date by update_multipliers.
+    pub max_health: u16,
+    /// Percent of damage dealt that heals the unit, from lifesteal buffs
+    pub lifesteal: u16,
     /// Type of unit this is
     pub unit_type: UnitType, 
 
@@ -183,48 +249,294 @@ pub struct Entity {
     pub stats: Option<UnitStats>,
     pub rarity: Option<Rarity>,
     pub special_trait: Option<SpecialTrait>,
+    /// Star level of a merged unit. 1 for a normal unit, 0 while hidden.
+    pub star_level: u8,
+    /// Bench slot of a piece parked off the board. Benched pieces don't count toward the piece limit and sit out the battle.
+    pub bench_slot: Option<u8>,
+
+    /// Fills up from attacking and being hit. At MAX_MANA the unit casts its signature ability.
+    pub mana: u16,
+    /// Timed effects on the unit. Counted down once per tick and removed when they run out.
+    pub buffs: Vec<EntityBuff>,
 }
 impl Entity {
-    pub fn walk_or_aa(&self, actions: &mut Actions, all_entities: &Entities) {
-        let enemy = if self.owner == Controller::Initializer {
+    pub fn is_benched(&self) -> bool {
+        self.bench_slot.is_some()
+    }
+
+    pub fn get_enemy(&self) -> Controller {
+        if self.owner == Controller::Initializer {
             Controller::Opponent
         } else {
             Controller::Initializer
+        }
+    }
+
+    /// Whether the unit should start casting its signature ability instead of walking or attacking
+    pub fn ready_to_cast(&self, all_entities: &Entities) -> bool {
+        self.mana >= MAX_MANA && abilities::get_signature_ability(self.unit_type)
+            .map_or(false, |ability| ability.can_cast(self, all_entities))
+    }
+
+    /// Enter the Ability state, spending all mana
+    pub fn start_ability(&self, actions: &mut Actions) {
+        let (cast_on, release_on) = abilities::get_signature_ability(self.unit_type).unwrap().timing();
+        actions.add(self.id, Action::ResetMana);
+        actions.add(self.id, Action::EntityStateChange { state: EntityState::Ability { progress: 0, cast_on, release_on } });
+    }
+
+    pub fn is_stunned(&self) -> bool {
+        self.buffs.iter().any(|buff| matches!(buff, EntityBuff::Stun{..}))
+    }
+
+    /// Untargetable units can't be picked as a new target, and current attackers drop them
+    pub fn is_untargetable(&self) -> bool {
+        self.buffs.iter().any(|buff| matches!(buff, EntityBuff::Untargetable{..}))
+    }
+
+    /// Total shield left across shield buffs
+    pub fn get_shield(&self) -> u16 {
+        self.buffs.iter().fold(0, |total, buff| match buff {
+            EntityBuff::Shield{amount, ..} => total.saturating_add(*amount),
+            _ => total,
+        })
+    }
+
+    /// Stats after multipliers: movement per tick, and the attack duration rounded up to whole ticks
+    pub fn get_effective_stats(&self) -> Option<UnitStats> {
+        let mut stats = self.stats.clone()?;
+        stats.movement_speed = self.get_movement_speed();
+        stats.attack_duration = ((stats.attack_duration as u32 * 100 + self.attack_speed_multiplier as u32 - 1) / (self.attack_speed_multiplier as u32).max(1)) as u16;
+        Some(stats)
+    }
+
+    /// Distance moved per tick
+    pub fn get_movement_speed(&self) -> u16 {
+        let movement_speed = self.stats.as_ref().map_or(0, |stats| stats.movement_speed);
+        (movement_speed as u32 * self.speed_multiplier as u32 / 100) as u16
+    }
+
+    /// Add a buff. Stun and untargetable keep the longest duration, and poison replaces the old poison.
+    /// Before BUFF_MERGING_VERSION everything else stacks, and buffs past MAX_BUFFS are dropped. From it, a buff of a kind
+    /// the unit already has merges into it, keeping the stronger effect and the longer duration.
+    pub fn apply_buff(&mut self, buff: EntityBuff, ruleset_version: u16) {
+        if self.state == EntityState::Dead {
+            return;
+        }
+        let merge = ruleset_version >= ruleset::BUFF_MERGING_VERSION;
+        let full = self.buffs.len() >= MAX_BUFFS;
+        let mut health_gain = 0;
+        let existing = self.buffs.iter_mut().find(|other| std::mem::discriminant(*other) == std::mem::discriminant(&buff));
+        match (existing, buff) {
+            (Some(EntityBuff::Stun{duration}), EntityBuff::Stun{duration: new_duration})
+            | (Some(EntityBuff::Untargetable{duration}), EntityBuff::Untargetable{duration: new_duration}) => {
+                *duration = (*duration).max(new_duration);
+            },
+            (Some(existing @ EntityBuff::Poison{..}), _) => {
+                *existing = buff;
+            },
+            (Some(EntityBuff::MaxHealth{duration, amount}), EntityBuff::MaxHealth{duration: new_duration, amount: new_amount}) if merge => {
+                // only the raise over the current buff comes with health
+                health_gain = new_amount.saturating_sub(*amount);
+                *duration = (*duration).max(new_duration);
+                *amount = (*amount).max(new_amount);
+            },
+            (Some(EntityBuff::Slow{duration, percent: value}), EntityBuff::Slow{duration: new_duration, percent: new_value})
+            | (Some(EntityBuff::Shield{duration, amount: value}), EntityBuff::Shield{duration: new_duration, amount: new_value})
+            | (Some(EntityBuff::AttackSpeed{duration, percent: value}), EntityBuff::AttackSpeed{duration: new_duration, percent: new_value})
+            | (Some(EntityBuff::Lifesteal{duration, percent: value}), EntityBuff::Lifesteal{duration: new_duration, percent: new_value}) if merge => {
+                *duration = (*duration).max(new_duration);
+                *value = (*value).max(new_value);
+            },
+            _ => {
+                if full {
+                    return;
+                }
+                if let EntityBuff::MaxHealth{amount, ..} = buff {
+                    health_gain = amount;
+                }
+                self.buffs.push(buff);
+            },
+        }
+        // stuns interrupt whatever the unit was doing
+        if let EntityBuff::Stun{..} = buff {
+            self.state = EntityState::Idle;
+        }
+        // the extra max health comes with as much health
+        self.health = self.health.saturating_add(health_gain);
+        self.update_multipliers();
+    }
+
+    /// Count down every buff by a tick and drop the expired ones
+    pub fn tick_buffs(&mut self) {
+        for buff in &mut self.buffs {
+            buff.tick();
+        }
+        self.buffs.retain(|buff| buff.get_duration() > 0);
+        self.update_multipliers();
+    }
+
+    /// Combine the base attack speed multiplier and stats with buffs. Slows multiply together, so two 50% slows leave a unit at 25% speed.
+    /// Attack speed buffs add up, then multiply the base. Max health and lifesteal buffs add up, and health is
+    /// brought down to the new max when a max health buff runs out.
+    pub fn update_multipliers(&mut self) {
+        self.speed_multiplier = self.buffs.iter().fold(100u32, |multiplier, buff| match buff {
+            EntityBuff::Slow{percent, ..} => multiplier * (100 - (*percent).min(100)) as u32 / 100,
+            _ => multiplier,
+        }) as u16;
+        let attack_speed_buffs = self.buffs.iter().fold(100u32, |total, buff| match buff {
+            EntityBuff::AttackSpeed{percent, ..} => total + *percent as u32,
+            _ => total,
+        });
+        self.attack_speed_multiplier = (self.base_attack_speed_multiplier as u32 * attack_speed_buffs / 100).min(u16::MAX as u32) as u16;
+        let starting_health = self.stats.as_ref().map_or(0, |stats| stats.starting_health);
+        let (max_health, lifesteal) = self.buffs.iter().fold((starting_health, 0u16), |(max_health, lifesteal), buff| match buff {
+            EntityBuff::MaxHealth{amount, ..} => (max_health.saturating_add(*amount), lifesteal),
+            EntityBuff::Lifesteal{percent, ..} => (max_health, lifesteal.saturating_add(*percent)),
+            _ => (max_health, lifesteal),
+        });
+        self.max_health = max_health;
+        self.lifesteal = lifesteal;
+        self.health = self.health.min(max_health);
+    }
+
+    /// Damage left after armor or magic resist: amount * 100 / (100 + resistance), rounded down.
+    /// Any hit that does damage still does at least 1.
+    pub fn mitigate(&self, amount: u16, damage_type: DamageType) -> u16 {
+        let stats = match self.stats.as_ref() {
+            Some(stats) => stats,
+            None => return amount,
+        };
+        let resistance = match damage_type {
+            DamageType::Physical => stats.armor,
+            DamageType::Magic => stats.magic_resist,
+            DamageType::True => return amount,
         };
+        if amount == 0 {
+            return 0;
+        }
+        ((amount as u32 * 100 / (100 + resistance as u32)) as u16).max(1)
+    }
+
+    /// Take damage, through shields first. Being hit also builds mana.
+    pub fn take_damage(&mut self, amount: u16) {
+        let mut amount = amount;
+        for buff in &mut self.buffs {
+            if let EntityBuff::Shield{amount: shield, ..} = buff {
+                let absorbed = amount.min(*shield);
+                *shield -= absorbed;
+                amount -= absorbed;
+            }
+        }
+        self.buffs.retain(|buff| !matches!(buff, EntityBuff::Shield{amount: 0, ..}));
+        self.mana = self.mana.saturating_add(MANA_PER_HIT).min(MAX_MANA);
+        match self.health.checked_sub(amount) {
+            Some(new_health) if new_health > 0 => {
+                self.health = new_health;
+            },
+            _ => {
+                self.health = 0;
+                self.state = EntityState::Dead;
+            }
+        }
+    }
+
+    /// Heal up to max health. Dead units can't be healed.
+    /// From HEALING_VERSION, part of the healing past max health becomes a short lived shield.
+    /// From OVERHEAL_CAP_VERSION, it tops up the unit's one shield instead, to at most a quarter of max health in total.
+    pub fn heal(&mut self, amount: u16, ruleset_version: u16) {
+        if self.state == EntityState::Dead {
+            return;
+        }
+        let healed = self.health.saturating_add(amount);
+        self.health = healed.min(self.max_health);
+        let overheal = healed - self.health;
+        if ruleset_version >= ruleset::HEALING_VERSION && overheal > 0 {
+            let cap = self.max_health / 4;
+            let shield = (overheal as u32 * OVERHEAL_SHIELD_PERCENT / 100).min(cap as u32) as u16;
+            if shield == 0 {
+                return;
+            }
+            if ruleset_version < ruleset::OVERHEAL_CAP_VERSION {
+                self.apply_buff(EntityBuff::Shield{duration: OVERHEAL_SHIELD_TICKS, amount: shield}, ruleset_version);
+                return;
+            }
+            match self.buffs.iter_mut().find(|buff| matches!(buff, EntityBuff::Shield{..})) {
+                Some(EntityBuff::Shield{duration, amount}) => {
+                    // a bigger shield from elsewhere is kept as it is
+                    *amount = (*amount).max(amount.saturating_add(shield).min(cap));
+                    *duration = (*duration).max(OVERHEAL_SHIELD_TICKS);
+                },
+                _ => self.buffs.push(EntityBuff::Shield{duration: OVERHEAL_SHIELD_TICKS, amount: shield}),
+            }
+        }
+    }
+
+    /// Walk towards the target, or start attacking it once in range.
+    /// From SPEED_SCALING_VERSION, attack progress is counted in percent of a tick (see Game::step) so attack speed isn't rounded to whole ticks.
+    /// From PATHFINDING_VERSION, units walk around other units instead of straight at their target.
+    /// From TARGETING_VERSION, new targets are picked by the unit's targeting policy rather than always the closest enemy.
+    /// `blockers` are the cells taken at the start of the tick, shared by every unit moving this tick.
+    pub fn walk_or_aa(&self, actions: &mut Actions, all_entities: &Entities, blockers: &pathfinding::Blockers, ruleset_version: u16) {
+        let scaled_attacks = ruleset_version >= ruleset::SPEED_SCALING_VERSION;
+        let enemy = self.get_enemy();
+        let policy = targeting::get_targeting_policy(self, ruleset_version);
         let target_entity = match self.target {
             Some(id) => {
                 let entity = all_entities.get_by_id(id).unwrap();
-                if entity.state == EntityState::Dead {
+                if entity.state == EntityState::Dead || entity.is_untargetable() {
                     actions.add(self.id, Action::Target { target_id: None });
                     None
                 } else {
-                    Some(EntityResult {
+                    let current = EntityResult {
                         entity: &entity,
                         distance: self.position.distance(&entity.position),
-                    })
+                    };
+                    let attack_range = self.stats.as_ref().map_or(0, |stats| stats.attack_range);
+                    match all_entities.find_closest_entity_in_range(&self.position, attack_range, enemy) {
+                        Some(closer) if policy == TargetingPolicy::Retarget && closer.distance < current.distance => Some(closer),
+                        _ => Some(current),
+                    }
                 }
             },
             None => {
-                all_entities.find_closest_entity(&self.position, enemy)
+                if ruleset_version >= ruleset::TARGETING_VERSION {
+                    targeting::find_target(all_entities, self, policy)
+                } else {
+                    all_entities.find_closest_entity(&self.position, enemy)
+                }
             }
         };
         match target_entity {
             Some(result) => {
                 let stats = self.stats.as_ref().unwrap();
                 let aa_range: u16 = stats.attack_range;
-                let movement_speed: u16 = stats.movement_speed;
-                let attack_duration: u16 = stats.attack_duration;
+                let movement_speed: u16 = self.get_movement_speed();
+                let attack_duration: u16 = if scaled_attacks {
+                    stats.attack_duration * 100
+                } else {
+                    ((stats.attack_duration as u32 * 100 / self.attack_speed_multiplier as u32) as u16).max(1)
+                };
 
                 if result.distance <= aa_range {
                     // ATTACK
                     actions.add(self.id, Action::EntityStateChange { state: EntityState::Attack{progress: 0, attack_on: attack_duration, target_id:result.entity.id}});
                 } else {
-                    let move_to = self.position.move_towards(&result.entity.position, result.distance, movement_speed);
+                    let move_to = if ruleset_version >= ruleset::PATHFINDING_VERSION {
+                        let max_expansions = if ruleset_version >= ruleset::PATH_BUDGET_VERSION { pathfinding::MAX_EXPANSIONS } else { usize::MAX };
+                        let waypoint = pathfinding::next_waypoint(&self.position, &result.entity.position, blockers, max_expansions);
+                        let distance = self.position.distance(&waypoint);
+                        // don't overshoot a cell center on the way
+                        let step = if waypoint == result.entity.position { movement_speed } else { movement_speed.min(distance) };
+                        self.position.move_towards(&waypoint, distance, step)
+                    } else {
+                        self.position.move_towards(&result.entity.position, result.distance, movement_speed)
+                    };
                     actions.add(self.id, Action::EntityStateChange { state: EntityState::Moving{to: move_to} });
                     actions.add(self.id, Action::Move{to: move_to});
                 }
 
-                if self.target == None {
+                if self.target != Some(result.entity.id) {
                     actions.add(self.id, Action::Target { target_id: Some(result.entity.id) });
                 }
             },
@@ -264,17 +576,61 @@ pub enum EntityState {
     Ult{progress: u16, cast_on: u16, release_on: u16},
 }
 
-// #[derive(Debug, PartialEq, Clone, AnchorSerialize, AnchorDeserialize, Copy, serde::Serialize, serde::Deserialize)]
-// pub enum EntityBuff {
-//     Untargetable{duration: u16},
-// }
+/// Timed effect on an entity. Durations are in ticks.
+#[derive(Debug, PartialEq, Clone, AnchorSerialize, AnchorDeserialize, Copy, serde::Serialize, serde::Deserialize)]
+pub enum EntityBuff {
+    /// Unit does nothing
+    Stun{duration: u16},
+    /// Lowers movement speed by a percent, through speed_multiplier
+    Slow{duration: u16, percent: u16},
+    /// Damage every tick
+    Poison{duration: u16, damage_per_tick: u16},
+    /// Absorbs damage before health. Removed early once used up.
+    Shield{duration: u16, amount: u16},
+    Untargetable{duration: u16},
+    /// Raises attack speed by a percent
+    AttackSpeed{duration: u16, percent: u16},
+    /// Raises max health, and health by as much when applied
+    MaxHealth{duration: u16, amount: u16},
+    /// Heals for a percent of damage dealt
+    Lifesteal{duration: u16, percent: u16},
+}
+
+impl EntityBuff {
+    pub fn get_duration(&self) -> u16 {
+        match *self {
+            EntityBuff::Stun{duration}
+            | EntityBuff::Slow{duration, ..}
+            | EntityBuff::Poison{duration, ..}
+            | EntityBuff::Shield{duration, ..}
+            | EntityBuff::Untargetable{duration}
+            | EntityBuff::AttackSpeed{duration, ..}
+            | EntityBuff::MaxHealth{duration, ..}
+            | EntityBuff::Lifesteal{duration, ..} => duration,
+        }
+    }
+
+    fn tick(&mut self) {
+        match self {
+            EntityBuff::Stun{duration}
+            | EntityBuff::Slow{duration, ..}
+            | EntityBuff::Poison{duration, ..}
+            | EntityBuff::Shield{duration, ..}
+            | EntityBuff::Untargetable{duration}
+            | EntityBuff::AttackSpeed{duration, ..}
+            | EntityBuff::MaxHealth{duration, ..}
+            | EntityBuff::Lifesteal{duration, ..} => *duration = duration.saturating_sub(1),
+        }
+    }
+}
 
-/// Enum denoting owner of an entity
+/// Enum denoting owner of an entity. Units keep their owner after they die; check EntityState::Dead for liveness.
 #[derive(Debug, PartialEq, AnchorDeserialize, AnchorSerialize, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub enum Controller {
     Opponent = 0,
     Initializer = 1,
     Contract = 2,
+    /// No longer assigned. Dead units used to be moved here, and old game accounts may still have them.
     Graveyard = 3,
 }
 
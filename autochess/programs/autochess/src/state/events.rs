@@ -0,0 +1,38 @@
+//! Things that happen while actions are applied. Game::step queues them during action application and handles them
+//! in a follow-up phase of the same tick, so traits and abilities can react to deaths, kills and damage.
+
+use std::collections::VecDeque;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GameEvent {
+    /// Damage from another unit was applied. `amount` is what got through shields. From PHASES_VERSION, when several
+    /// hits land on a unit in the same tick they share what got through by size.
+    DamageDealt{source_id: u16, target_id: u16, amount: u16},
+    /// A critical hit was applied
+    Crit{source_id: u16, target_id: u16, amount: u16},
+    /// A unit died. No killer for damage without a source, like poison, or from PHASES_VERSION when the kill is shared.
+    Death{entity_id: u16, killer_id: Option<u16>},
+    /// A unit dealt the killing blow. From PHASES_VERSION, the unit that dealt the victim the most damage in the tick
+    /// it died, with every unit tied for most getting the kill.
+    Kill{entity_id: u16, victim_id: u16},
+}
+
+/// Events in the order they were raised. Handled first in, first out so the result only depends on that order.
+#[derive(Debug, Default)]
+pub struct Events {
+    queue: VecDeque<GameEvent>,
+}
+
+impl Events {
+    pub fn push(&mut self, event: GameEvent) {
+        self.queue.push_back(event);
+    }
+
+    pub fn pop(&mut self) -> Option<GameEvent> {
+        self.queue.pop_front()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
@@ -0,0 +1,49 @@
+//! Integer math for the simulation. Floats can round differently on the BPF runtime and in the wasm client,
+//! so nothing that feeds into game state may use them.
+
+/// Floor of the square root
+pub fn isqrt(n: u64) -> u64 {
+    if n < 2 {
+        return n;
+    }
+    // Newton's method from an overestimate converges down onto the floor
+    let mut x = n;
+    let mut y = x / 2 + x % 2;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
+
+/// a * b / c rounded towards negative infinity, without overflowing the intermediate product
+pub fn mul_div_floor(a: i64, b: i64, c: i64) -> i64 {
+    (a as i128 * b as i128).div_euclid(c as i128) as i64
+}
+
+/// Whether numerator_a / denominator_a < numerator_b / denominator_b, compared exactly. Denominators must be positive.
+pub fn ratio_less_than(numerator_a: u32, denominator_a: u32, numerator_b: u32, denominator_b: u32) -> bool {
+    (numerator_a as u64) * (denominator_b as u64) < (numerator_b as u64) * (denominator_a as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn isqrt_floors_across_the_board() {
+        // every squared distance on an 800 x 800 board, plus the edges of u64
+        for n in 0..=(800 * 800 * 2) {
+            let root = isqrt(n);
+            assert!(root * root <= n && (root + 1) * (root + 1) > n, "isqrt({}) = {}", n, root);
+        }
+        assert_eq!(isqrt(u64::MAX), u32::MAX as u64);
+    }
+
+    #[test]
+    fn mul_div_floor_rounds_down() {
+        assert_eq!(mul_div_floor(7, 3, 2), 10);
+        assert_eq!(mul_div_floor(-7, 3, 2), -11);
+        assert_eq!(mul_div_floor(i64::MAX, 2, 4), i64::MAX / 2);
+    }
+}
@@ -1,8 +1,28 @@
-use anchor_lang::{solana_program::{hash::{Hash, hash, extend_and_hash}}, prelude::*};
+use std::collections::BTreeMap;
+
+use anchor_lang::{solana_program::{hash::{Hash, hash, hashv, extend_and_hash}}, prelude::*};
 
 use crate::state::entities;
 
-use super::{utils, entities::{Entities, EntityState}, units::{self, SpecialTrait}, actions::{Actions, Action}};
+use super::{utils, entities::{Entities, EntityState, EntityBuff}, units::{self, SpecialTrait}, actions::{Actions, Action, Hit, TickPhase}, merkle::{CardReveal, AssetReveal}, collection::CardAsset, deck::{Deck, DeckCard}, rounds::{self, PlayerEconomy, BoardUnit}, synergies, registry::UnitDefinition, ruleset::{self, Ruleset}, abilities, projectiles::{Projectiles, Projectile, ProjectileTarget}, fixed, collision, pathfinding, traits::{self, TraitEvent}, events::{Events, GameEvent}};
+use crate::ErrorCode;
+
+/// Space allocated for a game account, including the 8 byte discriminator.
+/// Must hold the largest state a game can reach, see worst_case_game_fits_its_account.
+pub const GAME_ACCOUNT_SPACE: usize = 4000;
+
+/// Number of bench slots each player has for parking hidden pieces off the board
+pub const BENCH_SIZE: u8 = 3;
+
+/// Ticks a battle may run before it's decided on remaining health, from HEALING_VERSION
+pub const BATTLE_TICK_LIMIT: u32 = 500;
+
+/// Rounds of events handled per tick, so a chain of reactions can't run forever. Events still queued after the last
+/// round are dropped without being handled. No trait or ability chains that deep; debug builds assert it stays that way.
+const MAX_EVENT_ROUNDS: usize = 8;
+
+/// In random hand mode, players may discard up to this many cards and use replacements drawn after the end of their hand
+pub const MULLIGAN_LIMIT: u8 = 2;
 
 use serde;
 
@@ -17,6 +37,7 @@ pub struct Game {
     /// 4 - Game is in progress. Waiting for crank request.
     /// continue 4 until game finishes or time limit is reached.
     /// 5 - Game is finished. Waiting for winning player to withdraw funds.
+    /// 6 - Draft hand mode only, between 1 and 2. Players alternately pick their hands from a shared pool.
     pub state: u8,
     pub wager: u64,
     pub piece_limit: u8,
@@ -39,6 +60,43 @@ pub struct Game {
     pub reveal_1: Option<[u8; 32]>,
     pub reveal_2: Option<[u8; 32]>,
 
+    /// How player hands are determined. Can only be changed by the initializer before an opponent joins.
+    pub hand_mode: HandMode,
+    /// Merkle roots of each player's hand, committed before the first reveal when hand_mode is Committed or Collection
+    pub i_hand_root: Option<[u8; 32]>,
+    pub o_hand_root: Option<[u8; 32]>,
+    /// Content hashes of each player's selected deck when hand_mode is Deck
+    pub i_deck_hash: Option<[u8; 32]>,
+    pub o_deck_hash: Option<[u8; 32]>,
+    /// Commitments to each player's hand adjustments, made during placement in random or draft hand mode.
+    /// See HandAdjustments::commitment.
+    pub i_adjustments_commitment: Option<[u8; 32]>,
+    pub o_adjustments_commitment: Option<[u8; 32]>,
+    /// Shared pool of cards generated from the first reveal when hand_mode is Draft
+    pub draft_pool: Vec<units::Card>,
+    /// Pool indices in the order they were picked. Players alternate, starting with draft_first_pick.
+    pub draft_picks: Vec<u8>,
+    pub draft_first_pick: entities::Controller,
+
+    /// Multi round games repeat shop (state 2) and battle (state 3) until a player runs out of hp
+    pub multi_round: bool,
+    /// Current round of a multi round game, starting at 1
+    pub round: u8,
+    pub i_economy: PlayerEconomy,
+    pub o_economy: PlayerEconomy,
+    /// Commitments to each player's share of the next round's randomness, made during the shop phase
+    pub i_round_commitment: Option<[u8; 32]>,
+    pub o_round_commitment: Option<[u8; 32]>,
+    /// Both players' revealed shares combined (xor). Shops from round 2 on are drawn from it.
+    pub round_randomness: Option<[u8; 32]>,
+
+    /// Unit stats snapshotted from the unit registry when the game is created. Empty means the built in stats.
+    pub unit_definitions: Vec<UnitDefinition>,
+    /// Version of the registry the definitions were taken from
+    pub registry_version: u32,
+    /// Rules the game was created under. Stats and simulation dispatch on this so games in progress and replays don't drift.
+    pub ruleset_version: u16,
+
     /// During placement phase, this indicates whether the player has locked in their state ahead of timer.
     pub i_locked_in: bool,
     pub o_locked_in: bool,
@@ -54,6 +112,7 @@ pub struct Game {
     pub o_inactivity_timer: Option<i64>,
 
     pub entities: Entities,
+    pub projectiles: Projectiles,
 
     pub tick: u32,
     pub random_calls: u16,
@@ -71,6 +130,42 @@ impl Default for WinCondition {
     fn default() -> Self { WinCondition::InProgress }
 }
 
+#[derive(Debug, PartialEq, Clone, AnchorSerialize, AnchorDeserialize, Copy, serde::Serialize, serde::Deserialize)]
+pub enum HandMode {
+    /// Hands are drawn with draw_hand from the first reveal and each player's second reveal
+    Random,
+    /// Players commit to a merkle root of an arbitrary hand and prove each placed card at the second reveal
+    Committed,
+    /// Like Committed, but leaves are card assets that must be owned by the player at the second reveal
+    Collection,
+    /// Hands are drawn by shuffling each player's selected constructed deck
+    Deck,
+    /// Players draft their hands from a shared pool before placement
+    Draft,
+}
+
+impl Default for HandMode {
+    fn default() -> Self { HandMode::Random }
+}
+
+/// Changes a player makes to their drawn hand. They're committed to during placement and declared at the second
+/// reveal, so they stay hidden until then but can't be chosen after the fact.
+#[derive(Debug, Default, PartialEq, Clone, AnchorSerialize, AnchorDeserialize, serde::Serialize, serde::Deserialize)]
+pub struct HandAdjustments {
+    /// Hand positions given up for mulligan replacements, in the order the replacements are drawn
+    pub discards: Vec<u8>,
+    /// Groups of three matching hand positions merged into the first position, applied in order
+    pub merges: Vec<[u8; 3]>,
+}
+
+impl HandAdjustments {
+    /// Hash committed to during placement. Salted with the secret of the player's second commitment, which stays
+    /// hidden until the second reveal.
+    pub fn commitment(&self, secret: &[u8; 32]) -> [u8; 32] {
+        hashv(&[&self.try_to_vec().unwrap(), secret]).to_bytes()
+    }
+}
+
 impl Game {
     /// initialize state for the client wasm
     pub fn new_client() -> Game {
@@ -89,13 +184,18 @@ impl Game {
         self.o_locked_in = false;
         self.placing_disabled = false;
         self.state = 0;
+        self.ruleset_version = ruleset::CURRENT_RULESET_VERSION;
         self.tick = 0;
         self.random_calls = 0;
         self.entities = entities::Entities {
             all: Vec::new(),
             counter: 0,
         };
+        self.projectiles = Projectiles::default();
         self.win_condition = WinCondition::InProgress;
+        self.hand_mode = HandMode::Random;
+        self.multi_round = false;
+        self.round = 0;
     }
 
     /// Check if a piece can be placed at a given location by a player, according the following rules
@@ -183,7 +283,7 @@ impl Game {
                 return None;
             }
             // Hand position must be in bounds
-            if hand_position >= self.hand_size {
+            if hand_position >= self.get_placeable_hand_size() {
                 return None;
             }
             // Hand position must be unique
@@ -210,13 +310,17 @@ impl Game {
         let x = grid_x * 100 + 50;
         let y = grid_y * 100 + 50;
 
-        if self.can_place(player, x, y, 0) {
+        // bringing a piece in from the bench adds one to the board
+        let benched = self.entities.all.iter()
+            .any(|entity| entity.owner == player && entity.unit_type == (units::UnitType::Hidden{hand_position}) && entity.is_benched());
+        let n = if benched { 1 } else { 0 };
+        if self.can_place(player, x, y, n) {
             // We can only place a hidden piece if we are a player
             if !(player == entities::Controller::Initializer || player == entities::Controller::Opponent) {
                 return None;
             }
             // Hand position must be in bounds
-            if hand_position >= self.hand_size {
+            if hand_position >= self.get_placeable_hand_size() {
                 return None;
             }
 
@@ -228,6 +332,7 @@ impl Game {
                             if hand_position_compare == hand_position {
                                     // This piece has already been placed. Move it.
                                     entity.position = utils::Location{x, y};
+                                    entity.bench_slot = None;
                                     return Some(entity.id);
                             }
                         }
@@ -240,6 +345,48 @@ impl Game {
             return None;
         }
     }
+    /// Park a hidden piece on the bench. If the hand position hasn't been placed yet it goes straight to the bench,
+    /// otherwise the placed piece is moved off the board.
+    pub fn bench_piece_hidden(&mut self, player: entities::Controller, bench_slot: u8, hand_position: u8) -> Option<u16> {
+        if !(player == entities::Controller::Initializer || player == entities::Controller::Opponent) {
+            return None;
+        }
+        if bench_slot >= BENCH_SIZE || hand_position >= self.get_placeable_hand_size() {
+            return None;
+        }
+        if self.entities.get_benched(player, bench_slot).is_some() {
+            return None;
+        }
+        for entity in &mut self.entities.all {
+            if entity.owner == player && entity.unit_type == (units::UnitType::Hidden{hand_position}) {
+                entity.bench_slot = Some(bench_slot);
+                return Some(entity.id);
+            }
+        }
+        let id = self.entities.create_hidden(player, 0, 0, hand_position);
+        self.entities.get_by_id_mut(id)?.bench_slot = Some(bench_slot);
+        Some(id)
+    }
+
+    /// Swap two placed hidden pieces, including between the board and the bench
+    pub fn swap_piece_hidden(&mut self, player: entities::Controller, hand_position_a: u8, hand_position_b: u8) -> Option<u16> {
+        let find = |entities: &entities::Entities, hand_position: u8| {
+            entities.all.iter()
+                .position(|entity| entity.owner == player && entity.unit_type == (units::UnitType::Hidden{hand_position}))
+        };
+        let a = find(&self.entities, hand_position_a)?;
+        let b = find(&self.entities, hand_position_b)?;
+        if a == b {
+            return None;
+        }
+        let (position_a, bench_a) = (self.entities.all[a].position, self.entities.all[a].bench_slot);
+        self.entities.all[a].position = self.entities.all[b].position;
+        self.entities.all[a].bench_slot = self.entities.all[b].bench_slot;
+        self.entities.all[b].position = position_a;
+        self.entities.all[b].bench_slot = bench_a;
+        Some(self.entities.all[a].id)
+    }
+
     /// Remove a hidden piece piece in the grid.
     pub fn remove_piece_hidden(&mut self, player: entities::Controller, hand_position: u8) -> Option<u16> {
         // We can only remove a hidden piece if we are a player
@@ -247,7 +394,7 @@ impl Game {
             return None;
         }
         // Hand position must be in bounds
-        if hand_position >= self.hand_size {
+        if hand_position >= self.get_placeable_hand_size() {
             return None;
         }
         // delete if we find the hand position, otherwise fail
@@ -267,228 +414,1314 @@ impl Game {
         }
         return None;
     }
+    /// Ruleset that unit stats are looked up in for this game
+    pub fn ruleset(&self) -> Ruleset {
+        Ruleset { version: self.ruleset_version, unit_definitions: self.unit_definitions.clone() }
+    }
+
+    /// Hand positions that can be placed. In random mode this includes the mulligan replacements,
+    /// which sit at hand_size..hand_size + MULLIGAN_LIMIT.
+    pub fn get_placeable_hand_size(&self) -> u8 {
+        if self.hand_mode == HandMode::Random {
+            self.hand_size + MULLIGAN_LIMIT
+        } else {
+            self.hand_size
+        }
+    }
+
     /// Using second reveal, simulate the player's draw. Then fill in identities of the hidden pieces.
-    pub fn reveal_hidden_pieces(&mut self, player: entities::Controller, reveal_2: &[u8; 32]) {
-        let hand = draw_hand(self.hand_size, &self.reveal_1.unwrap(), reveal_2);
+    /// In draft mode the hand is whatever the player picked.
+    /// Mulligans and merges the player declared are applied to the hand before revealing.
+    pub fn reveal_hidden_pieces(&mut self, player: entities::Controller, reveal_2: &[u8; 32], adjustments: &HandAdjustments) -> ProgramResult {
+        let discards = &adjustments.discards;
+        if !self.validate_mulligan(player, discards) {
+            return Err(ErrorCode::MulliganError.into());
+        }
+        let mut hand = if self.hand_mode == HandMode::Draft {
+            self.get_draft_hand(player)
+        } else {
+            // replacements are just the next cards in the same draw
+            draw_hand(self.get_placeable_hand_size(), &self.reveal_1.unwrap(), reveal_2, &self.ruleset())
+        };
+
+        // discarded cards and replacements that weren't unlocked can't be merged
+        let mut unavailable = discards.clone();
+        unavailable.extend((self.hand_size + discards.len() as u8)..(hand.len() as u8));
+        let consumed = apply_merges(&mut hand, &adjustments.merges, &unavailable, &self.ruleset()).ok_or(ErrorCode::MergeError)?;
+        let is_consumed = |entity: &entities::Entity| {
+            entity.owner == player && matches!(entity.unit_type, units::UnitType::Hidden{hand_position} if consumed.contains(&hand_position))
+        };
+        if self.entities.all.iter().any(|entity| is_consumed(entity) && !entity.is_benched()) {
+            return Err(ErrorCode::MergeError.into());
+        }
+        // benched pieces that were merged away leave the bench
+        self.entities.all.retain(|entity| !is_consumed(entity));
         self.entities.reveal_all_hidden(player, &hand);
+        Ok(())
     }
 
-    /// Check if the game has been completed and update account with who won
-    pub fn update_win_condition(&mut self) {
-        let mut i_alive = 0;
-        let mut o_alive = 0;
+    /// Store a player's commitment to their hand adjustments. Can be replaced until placement is over.
+    pub fn commit_adjustments(&mut self, player: entities::Controller, commitment: [u8; 32], now: i64) -> ProgramResult {
+        if self.hand_mode != HandMode::Random && self.hand_mode != HandMode::Draft {
+            return Err(ErrorCode::InvalidGameSettings.into());
+        }
+        if self.placing_disabled || self.piece_timer.map_or(true, |piece_timer| now > piece_timer) {
+            return Err(ErrorCode::TimeError.into());
+        }
+        match player {
+            entities::Controller::Initializer => self.i_adjustments_commitment = Some(commitment),
+            _ => self.o_adjustments_commitment = Some(commitment),
+        }
+        Ok(())
+    }
+
+    /// Whether adjustments declared at the second reveal are the ones the player committed to during placement.
+    /// A hand left as drawn needs no commitment.
+    pub fn validate_adjustments(&self, player: entities::Controller, adjustments: &HandAdjustments, secret: &[u8; 32]) -> bool {
+        if *adjustments == HandAdjustments::default() {
+            return true;
+        }
+        let commitment = match player {
+            entities::Controller::Initializer => self.i_adjustments_commitment,
+            _ => self.o_adjustments_commitment,
+        };
+        commitment == Some(adjustments.commitment(secret))
+    }
+
+    /// Check a player's discards against their placed pieces:
+    ///  - at most MULLIGAN_LIMIT unique discards, all from the original hand
+    ///  - no discarded card was placed
+    ///  - replacement i was only placed if at least i + 1 cards were discarded
+    fn validate_mulligan(&self, player: entities::Controller, discards: &Vec<u8>) -> bool {
+        if discards.len() > MULLIGAN_LIMIT as usize {
+            return false;
+        }
+        for (i, discard) in discards.iter().enumerate() {
+            if *discard >= self.hand_size || discards[..i].contains(discard) {
+                return false;
+            }
+        }
         for entity in &self.entities.all {
-            if entity.state != entities::EntityState::Dead {
-                match entity.owner {
-                    entities::Controller::Initializer => {
-                        i_alive += 1;
-                    },
-                    entities::Controller::Opponent => {
-                        o_alive += 1;
-                    },
-                    _other => {}
+            if entity.owner != player {
+                continue;
+            }
+            if let units::UnitType::Hidden{hand_position} = entity.unit_type {
+                if discards.contains(&hand_position) {
+                    return false;
+                }
+                if hand_position >= self.hand_size && (hand_position - self.hand_size) as usize >= discards.len() {
+                    return false;
                 }
             }
         }
-        if i_alive == 0 && o_alive == 0 {
-            self.win_condition = WinCondition::Tie;
-        } else if i_alive == 0 {
-            self.win_condition = WinCondition::Opponent;
-        } else if o_alive == 0 {
-            self.win_condition = WinCondition::Initializer;
+        true
+    }
+
+    /// Generate the shared pool from the first reveal and start the timer for the first pick.
+    /// The pool holds enough cards for both hands plus a few extra so the last picks are still choices.
+    pub fn start_draft(&mut self, now: i64) {
+        let pool_size = self.hand_size * 2 + 4;
+        self.draft_pool = draw_hand(pool_size, &self.reveal_1.unwrap(), &[0; 32], &self.ruleset());
+        self.draft_picks = Vec::new();
+        // first pick goes to whoever the first reveal favours
+        self.draft_first_pick = if self.reveal_1.unwrap()[31] % 2 == 0 {
+            entities::Controller::Initializer
+        } else {
+            entities::Controller::Opponent
+        };
+        self.state = 6;
+        self.set_pick_timer(now);
+    }
+
+    /// Player whose turn it is to pick from the draft pool
+    pub fn get_draft_turn(&self) -> entities::Controller {
+        if self.draft_picks.len() % 2 == 0 {
+            self.draft_first_pick
+        } else if self.draft_first_pick == entities::Controller::Initializer {
+            entities::Controller::Opponent
         } else {
-            self.win_condition = WinCondition::InProgress;
+            entities::Controller::Initializer
         }
     }
 
-    /// Retreive a random number derived from the second reveal
-    fn get_random_u8(&mut self) -> u8 {
-        if self.random_calls >= 32 {
-            self.reveal_2 = Some(generate_new_randomness(&self.reveal_2.unwrap()));
-            self.random_calls = 0;
+    /// Only the player on the clock can be claimed inactive during the draft
+    fn set_pick_timer(&mut self, now: i64) {
+        // 30 seconds per pick
+        let inactivity_timer: i64 = now + 30;
+        if self.get_draft_turn() == entities::Controller::Initializer {
+            self.i_inactivity_timer = Some(inactivity_timer);
+            self.o_inactivity_timer = None;
+        } else {
+            self.o_inactivity_timer = Some(inactivity_timer);
+            self.i_inactivity_timer = None;
         }
-        let randomness = self.reveal_2.unwrap();
-        let index = (self.random_calls)as usize;
-        self.random_calls = self.random_calls + 1;
-        return randomness[index];
     }
 
-    /// Run through one game step (every entity moves)
-    pub fn step(&mut self) {
+    /// Pick a card out of the draft pool. Once both hands are full, advance to placement (state 2).
+    pub fn draft_pick(&mut self, player: entities::Controller, pool_index: u8, now: i64) -> ProgramResult {
+        if self.get_draft_turn() != player {
+            return Err(ErrorCode::DraftError.into());
+        }
+        if pool_index as usize >= self.draft_pool.len() || self.draft_picks.contains(&pool_index) {
+            return Err(ErrorCode::DraftError.into());
+        }
+        self.draft_picks.push(pool_index);
 
-        let mut actions: Actions = Actions::new();
-        // loop through entities and queue actions
-        let all_entities = &self.entities.clone();
-        for entity in &self.entities.all.clone() {
-            if entity.owner == entities::Controller::Initializer || entity.owner == entities::Controller::Opponent {
+        if self.draft_picks.len() >= self.hand_size as usize * 2 {
+            self.state = 2;
+            self.o_inactivity_timer = None;
+            self.i_inactivity_timer = None;
+            let piece_timer: i64 = now + 90 + 5;
+            self.piece_timer = Some(piece_timer);
+        } else {
+            self.set_pick_timer(now);
+        }
+        Ok(())
+    }
 
-                if self.tick == 0 {
-                    if entity.special_trait == Some(SpecialTrait::Assassin) {
-                        entity.assassin_hop(&mut actions);
-                    }
-                } else {
-                    match entity.state {
-                        entities::EntityState::Idle => {
-                            entity.walk_or_aa(&mut actions, all_entities);
-                        },
-                        entities::EntityState::Moving{to: _} => {
-                            entity.walk_or_aa(&mut actions, all_entities);
-                        },
-                        entities::EntityState::Attack{progress, attack_on, target_id} => {
-                            let new_progress = progress + 1;
-                            if new_progress >= attack_on {
-                                let stats = entity.stats.as_ref().unwrap();
+    /// Cards drafted by a player, in pick order. This is their hand.
+    pub fn get_draft_hand(&self, player: entities::Controller) -> Vec<units::Card> {
+        let first = if self.draft_first_pick == player { 0 } else { 1 };
+        self.draft_picks.iter()
+            .skip(first)
+            .step_by(2)
+            .map(|pool_index| self.draft_pool[*pool_index as usize].clone())
+            .collect()
+    }
 
-                                let mut attack_damage = stats.attack_damage;
-                                // calculate crit
-                                let random = self.get_random_u8();
-                                if random < stats.crit_chance {
-                                    attack_damage = attack_damage * 2;
-                                }
-                                // attack
-                                actions.add(target_id, Action::Damage{amount: attack_damage});
-                                actions.add(entity.id, Action::EntityStateChange { state: EntityState::Idle });
-                            } else {
-                                actions.add(entity.id, Action::EntityStateChange { 
-                                    state: EntityState::Attack { progress: new_progress, attack_on, target_id }
-                                });
-                            }
-                        },
-                        entities::EntityState::Airborne { progress, finish_on, to } => {
-                            let new_progress = progress + 1;
-                            if new_progress >= finish_on {
-                                actions.add(entity.id, Action::Move { to });
-                                actions.add(entity.id, Action::EntityStateChange { state: EntityState::Idle });
-                            } else {
-                                let distance = entity.position.distance(&to);
-                                let move_to = entity.position.move_towards(&to, distance, distance / finish_on);
-                                actions.add(entity.id, Action::Move { to: move_to});
-                                actions.add(entity.id, Action::EntityStateChange { 
-                                    state: EntityState::Airborne { progress: new_progress, finish_on, to }
-                                });
-                            }
-                        }
-                        _other=>{}
-                    }
-                }
-            }
+    /// Using second reveal, shuffle the player's selected deck and draw from it. Then fill in identities of the hidden pieces.
+    pub fn reveal_deck_pieces(&mut self, player: entities::Controller, reveal_2: &[u8; 32], deck: &Deck) -> ProgramResult {
+        let deck_hash = match player {
+            entities::Controller::Initializer => self.i_deck_hash,
+            entities::Controller::Opponent => self.o_deck_hash,
+            _ => None,
         };
+        if deck_hash != Some(deck.content_hash()) {
+            return Err(ErrorCode::InvalidDeck.into());
+        }
+        let hand = draw_hand_from_deck(self.hand_size, &self.reveal_1.unwrap(), reveal_2, &deck.cards, &self.ruleset());
+        self.entities.reveal_all_hidden(player, &hand);
+        Ok(())
+    }
 
-        // apply actions
-        for entity in &mut self.entities.all {
-            match actions.get_actions_by_id(&entity.id) {
-                Some(actions_for_id) => {
-                    for action in actions_for_id {
-                        match *action {
-                            Action::Damage{amount} => {
-                                let new_health_option =  entity.health.checked_sub(amount);
-                                if let Some(new_health) = new_health_option{
-                                    if new_health == 0 {
-                                        entity.health = 0;
-                                        entity.state = entities::EntityState::Dead;
-                                        entity.owner = entities::Controller::Graveyard;
-                                    } else {
-                                        entity.health = new_health;
-                                    }
-                                } else {
-                                    entity.health = 0;
-                                    entity.state = entities::EntityState::Dead;
-                                    entity.owner = entities::Controller::Graveyard;
-                                }
-                            },
-                            Action::EntityStateChange { state } => {
-                                if entity.state != EntityState::Dead {
-                                    entity.state = state;
-                                }
-                            },
-                            Action::Move { to } => {
-                                entity.position = to;
-                            },
-                            Action::Target { target_id } => {
-                                entity.target = target_id;
-                            }
-                        }
-                    }
-                },
-                None => {}
+    /// Snapshot a player's deck, which must be owned by the player's wallet. Each player selects once, before either
+    /// player's first reveal, so nobody can pick a deck after learning the randomness it will be shuffled with.
+    pub fn select_deck(&mut self, player: entities::Controller, deck: &Deck) -> ProgramResult {
+        if self.hand_mode != HandMode::Deck || deck.cards.len() < self.hand_size as usize {
+            return Err(ErrorCode::InvalidGameSettings.into());
+        }
+        let wallet = match player {
+            entities::Controller::Initializer => self.initializer,
+            _ => self.opponent,
+        };
+        if deck.owner != wallet {
+            return Err(ErrorCode::OwnershipError.into());
+        }
+        let has_revealed = match player {
+            entities::Controller::Initializer => self.i_has_revealed,
+            _ => self.o_has_revealed,
+        };
+        if has_revealed {
+            return Err(ErrorCode::RevealError.into());
+        }
+        match player {
+            entities::Controller::Initializer if self.i_deck_hash == None => {
+                self.i_deck_hash = Some(deck.content_hash());
+            },
+            entities::Controller::Opponent if self.o_deck_hash == None => {
+                self.o_deck_hash = Some(deck.content_hash());
+            },
+            _ => {
+                return Err(ErrorCode::RevealError.into());
             }
         }
-        self.tick = self.tick + 1;
+        Ok(())
     }
-    pub fn get_player_type(&self, burner_wallet: Pubkey) -> entities::Controller {
-        if self.i_burner == burner_wallet {
-            entities::Controller::Initializer
-        } else {
-            entities::Controller::Opponent
+
+    /// Choose how hands are determined. A draft can't feed a multi round game, whose boards come from the shop.
+    pub fn configure_hand_mode(&mut self, hand_mode: HandMode) -> ProgramResult {
+        if hand_mode == HandMode::Draft && self.multi_round {
+            return Err(ErrorCode::InvalidGameSettings.into());
         }
+        self.hand_mode = hand_mode;
+        Ok(())
     }
 
-    pub fn lock_in_player(&mut self, player_type: entities::Controller) -> ProgramResult {
-        match player_type {
-            entities::Controller::Initializer => {
-                self.i_locked_in = true;
-            }
-            entities::Controller::Opponent => {
-                self.o_locked_in = true;
-            }
-            _ => {
-                return Err(ProgramError::InvalidArgument);
-            }
+    /// Turn the game into a multi round game. Draft games can't be, since the draft replaces the first round's shop.
+    pub fn configure_multi_round(&mut self) -> ProgramResult {
+        if self.hand_mode == HandMode::Draft {
+            return Err(ErrorCode::InvalidGameSettings.into());
         }
+        self.multi_round = true;
         Ok(())
     }
 
-    pub fn both_players_locked(&self) -> bool {
-        self.i_locked_in && self.o_locked_in
+    /// Whether what a player's hand is drawn from is fixed, so they can make their first reveal.
+    /// In deck mode both players' decks must be selected, so neither can pick a deck against known randomness.
+    pub fn hand_source_locked(&self, player: entities::Controller) -> bool {
+        match self.hand_mode {
+            HandMode::Random | HandMode::Draft => true,
+            HandMode::Committed | HandMode::Collection => match player {
+                entities::Controller::Initializer => self.i_hand_root.is_some(),
+                _ => self.o_hand_root.is_some(),
+            },
+            HandMode::Deck => self.i_deck_hash.is_some() && self.o_deck_hash.is_some(),
+        }
     }
-}
 
-pub fn validate_reveal(stored_hash: &[u8; 32], reveal: &[u8; 32], secret: &[u8; 32]) -> bool {
-    let hash = extend_and_hash(&Hash::new_from_array(*reveal), secret);
-    if Hash::new_from_array(*stored_hash) != hash {
-        return false;
+    /// Store a player's hand root. Roots are fixed once that player has made their first reveal.
+    pub fn commit_hand(&mut self, player: entities::Controller, hand_root: [u8; 32]) -> ProgramResult {
+        if self.hand_mode != HandMode::Committed && self.hand_mode != HandMode::Collection {
+            return Err(ErrorCode::InvalidGameSettings.into());
+        }
+        match player {
+            entities::Controller::Initializer if !self.i_has_revealed => {
+                self.i_hand_root = Some(hand_root);
+            },
+            entities::Controller::Opponent if !self.o_has_revealed => {
+                self.o_hand_root = Some(hand_root);
+            },
+            _ => {
+                return Err(ErrorCode::RevealError.into());
+            }
+        }
+        Ok(())
     }
-    return true;
-}
 
-pub fn generate_new_randomness(stored_hash: &[u8; 32]) -> [u8; 32] {
-    hash(stored_hash).to_bytes()
-}
+    /// Fill in identities of the hidden pieces from cards proven against the player's committed hand root.
+    /// Only placed cards are revealed. Every hidden piece of the player must be covered.
+    pub fn reveal_committed_pieces(&mut self, player: entities::Controller, cards: &Vec<CardReveal>) -> ProgramResult {
+        let hand_root = self.get_hand_root(player).ok_or(ErrorCode::RevealError)?;
 
-/// Get element from array based on random, which is in [0,256).
-fn get_from_p_array<T: Clone>(array: &[(T, u8)], random: u8) -> T {
-    let total_p = array.iter().fold(0, |acc, (_, p)| acc + p);
-    let random_in_range = random as f32 / 256.0 * total_p as f32; // [0, total_p)
-    let mut running_total = 0;
-    for (item, weight) in array.iter() {
-        running_total += weight;
-        if random_in_range < running_total as f32 {
-            return item.clone();
+        for reveal in cards {
+            if reveal.hand_position >= self.hand_size || !reveal.card.has_valid_stats(&self.ruleset()) || !reveal.verify(&hand_root) {
+                return Err(ErrorCode::ProofError.into());
+            }
+            // a card that wasn't placed (or was already revealed) has nothing to fill in
+            if self.entities.reveal_hidden_at(player, reveal.hand_position, &reveal.card) == None {
+                return Err(ErrorCode::ProofError.into());
+            }
+        }
+        if self.entities.has_hidden(player) {
+            return Err(ErrorCode::ProofError.into());
         }
+        Ok(())
     }
-    panic!("random_in_range {} is out of range", random_in_range);
-}
 
-/// Draw HAND_SIZE cards from deck using randomness of first reveal and second commit. Client side, but verified on chain.
-pub fn draw_hand(hand_size: u8, randomness1: &[u8; 32], randomness2: &[u8; 32]) -> Vec<units::Card> {
-    // XOR randomness together
-    msg!("drawing");
-    let mut reveal = randomness1.clone();
-    reveal.iter_mut()
-        .zip(randomness2.iter())
-        .for_each(|(x1, x2)| *x1 ^= *x2);
+    /// Fill in identities of the hidden pieces from card assets proven against the player's committed hand root.
+    /// assets[i] is the account for reveals[i], and must currently be owned by the player.
+    pub fn reveal_collection_pieces(&mut self, player: entities::Controller, reveals: &Vec<AssetReveal>, assets: &Vec<CardAsset>) -> ProgramResult {
+        let hand_root = self.get_hand_root(player).ok_or(ErrorCode::RevealError)?;
+        let wallet = match player {
+            entities::Controller::Initializer => self.initializer,
+            _ => self.opponent,
+        };
+        if reveals.len() != assets.len() {
+            return Err(ErrorCode::ProofError.into());
+        }
 
-    let mut result: Vec<units::Card> = Vec::new();
+        for (i, (reveal, asset)) in reveals.iter().zip(assets.iter()).enumerate() {
+            // the same asset can't fill two hand positions
+            if reveals[..i].iter().any(|other| other.asset == reveal.asset) {
+                return Err(ErrorCode::ProofError.into());
+            }
+            if asset.owner != wallet {
+                return Err(ErrorCode::OwnershipError.into());
+            }
+            if reveal.hand_position >= self.hand_size || !reveal.verify(&hand_root) {
+                return Err(ErrorCode::ProofError.into());
+            }
+            if self.entities.reveal_hidden_at(player, reveal.hand_position, &asset.card) == None {
+                return Err(ErrorCode::ProofError.into());
+            }
+        }
+        if self.entities.has_hidden(player) {
+            return Err(ErrorCode::ProofError.into());
+        }
+        Ok(())
+    }
 
-    // Array of unit type and the relative probability
-    let deck = [
-        (units::UnitType::Wolf, 1 as u8),
-        (units::UnitType::Bear, 1),
-        (units::UnitType::Bull, 1),
-    ];
+    fn get_hand_root(&self, player: entities::Controller) -> Option<[u8; 32]> {
+        match player {
+            entities::Controller::Initializer => self.i_hand_root,
+            entities::Controller::Opponent => self.o_hand_root,
+            _ => None,
+        }
+    }
 
-    let rarities = [
-        (units::Rarity::Common, 150 as u8),
+    /// Validate a player's second commitment and fold it into the game's second reveal.
+    /// Inactivity timer is set for the opposing player.
+    pub fn accept_second_reveal(&mut self, player: entities::Controller, reveal_2: &[u8; 32], secret: &[u8; 32], now: i64) -> ProgramResult {
+        // Error if there is still time left, unless placing is disabled (meaning both players have locked in).
+        if now < self.piece_timer.unwrap() && !self.placing_disabled {
+            return Err(ErrorCode::TimeError.into());
+        }
+        if !self.placing_disabled {
+            self.placing_disabled = true;
+        }
+
+        // opposing player will be inactive 60 seconds after the first player's reveal
+        let inactivity_timer: i64 = now + 60;
+        if player == entities::Controller::Initializer && !self.i_has_revealed {
+            if !validate_reveal(&self.i_commitment_2.unwrap(), reveal_2, secret) {
+                return Err(ErrorCode::RevealError.into());
+            }
+            self.i_has_revealed = true;
+            self.i_commitment_2 = None;
+
+            self.o_inactivity_timer = Some(inactivity_timer);
+        } else if player == entities::Controller::Opponent && !self.o_has_revealed {
+            if !validate_reveal(&self.o_commitment_2.unwrap(), reveal_2, secret) {
+                return Err(ErrorCode::RevealError.into());
+            }
+            self.o_has_revealed = true;
+            self.o_commitment_2 = None;
+
+            self.i_inactivity_timer = Some(inactivity_timer);
+        } else {
+            return Err(ErrorCode::RevealError.into());
+        }
+        // Combine reveals (xor)
+        match &mut self.reveal_2 {
+            None => {
+                self.reveal_2 = Some(*reveal_2);
+            },
+            Some(stored_reveal) => {
+                stored_reveal.iter_mut()
+                    .zip(reveal_2.iter())
+                    .for_each(|(x1, x2)| *x1 ^= *x2);
+            }
+        }
+        Ok(())
+    }
+
+    /// Advance to state 3 once both players have made their second reveal
+    pub fn finish_second_reveal(&mut self) {
+        if self.i_has_revealed && self.o_has_revealed {
+            self.state = 3;
+            if self.multi_round {
+                self.load_boards();
+            }
+            self.i_has_revealed = false;
+            self.o_has_revealed = false;
+            self.i_adjustments_commitment = None;
+            self.o_adjustments_commitment = None;
+
+            // Any inactivity timers are stopped because both players have revealed
+            self.o_inactivity_timer = None;
+            self.i_inactivity_timer = None;
+        }
+    }
+
+    /// Check if the game has been completed and update account with who won.
+    /// In multi round games, the game is only over when a player runs out of hp.
+    pub fn update_win_condition(&mut self) {
+        if self.multi_round {
+            let i_hp = self.i_economy.hp;
+            let o_hp = self.o_economy.hp;
+            self.win_condition = if i_hp == 0 && o_hp == 0 {
+                WinCondition::Tie
+            } else if i_hp == 0 {
+                WinCondition::Opponent
+            } else if o_hp == 0 {
+                WinCondition::Initializer
+            } else {
+                WinCondition::InProgress
+            };
+        } else {
+            self.win_condition = self.get_battle_result();
+        }
+    }
+
+    /// Result of the current battle according to the units left alive.
+    /// From HEALING_VERSION, a battle still going at BATTLE_TICK_LIMIT goes to the side with the larger share of its
+    /// max health left, so units healing each other can't stall forever.
+    pub fn get_battle_result(&self) -> WinCondition {
+        let mut i_alive = 0;
+        let mut o_alive = 0;
+        // (health, max health) left on each side
+        let mut i_health = (0u32, 0u32);
+        let mut o_health = (0u32, 0u32);
+        for entity in &self.entities.all {
+            if entity.state != entities::EntityState::Dead && !entity.is_benched() {
+                match entity.owner {
+                    entities::Controller::Initializer => {
+                        i_alive += 1;
+                        i_health = (i_health.0 + entity.health as u32, i_health.1 + entity.max_health as u32);
+                    },
+                    entities::Controller::Opponent => {
+                        o_alive += 1;
+                        o_health = (o_health.0 + entity.health as u32, o_health.1 + entity.max_health as u32);
+                    },
+                    _other => {}
+                }
+            }
+        }
+        if i_alive == 0 && o_alive == 0 {
+            WinCondition::Tie
+        } else if i_alive == 0 {
+            WinCondition::Opponent
+        } else if o_alive == 0 {
+            WinCondition::Initializer
+        } else if self.ruleset_version >= ruleset::HEALING_VERSION && self.tick >= BATTLE_TICK_LIMIT {
+            if fixed::ratio_less_than(o_health.0, o_health.1.max(1), i_health.0, i_health.1.max(1)) {
+                WinCondition::Initializer
+            } else if fixed::ratio_less_than(i_health.0, i_health.1.max(1), o_health.0, o_health.1.max(1)) {
+                WinCondition::Opponent
+            } else {
+                WinCondition::Tie
+            }
+        } else {
+            WinCondition::InProgress
+        }
+    }
+
+    fn get_economy_mut(&mut self, player: entities::Controller) -> Option<&mut PlayerEconomy> {
+        match player {
+            entities::Controller::Initializer => Some(&mut self.i_economy),
+            entities::Controller::Opponent => Some(&mut self.o_economy),
+            _ => None,
+        }
+    }
+
+    /// Fill a player's shop from the round's randomness and their reroll count. The first round's randomness is the
+    /// first reveal; after that it's what both players revealed during the previous battle.
+    fn refresh_shop(&mut self, player: entities::Controller) {
+        let round = self.round;
+        let reveal_1 = self.reveal_1.unwrap();
+        let round_randomness = self.round_randomness.unwrap_or(reveal_1);
+        let ruleset = self.ruleset();
+        if let Some(economy) = self.get_economy_mut(player) {
+            let randomness = rounds::shop_randomness(&round_randomness, player, round, economy.rerolls);
+            economy.shop = draw_hand(rounds::SHOP_SIZE, &reveal_1, &randomness, &ruleset).into_iter().map(Some).collect();
+        }
+    }
+
+    /// Start the shop phase (state 2) of the next round. Called after the first reveal and after every finished round.
+    pub fn start_round(&mut self, now: i64) {
+        if self.round == 0 {
+            self.i_economy = PlayerEconomy::new();
+            self.o_economy = PlayerEconomy::new();
+        } else {
+            self.i_economy.gold = self.i_economy.gold.saturating_add(rounds::GOLD_PER_ROUND);
+            self.o_economy.gold = self.o_economy.gold.saturating_add(rounds::GOLD_PER_ROUND);
+        }
+        self.round += 1;
+        self.i_economy.rerolls = 0;
+        self.o_economy.rerolls = 0;
+        self.i_has_revealed = false;
+        self.o_has_revealed = false;
+        self.i_round_commitment = None;
+        self.o_round_commitment = None;
+        self.i_inactivity_timer = None;
+        self.o_inactivity_timer = None;
+        self.refresh_shop(entities::Controller::Initializer);
+        self.refresh_shop(entities::Controller::Opponent);
+
+        self.state = 2;
+        self.i_locked_in = false;
+        self.o_locked_in = false;
+        self.placing_disabled = false;
+        let piece_timer: i64 = now + 90 + 5;
+        self.piece_timer = Some(piece_timer);
+    }
+
+    /// Commit to a share of the next round's randomness. Can be replaced until the shop phase is over.
+    pub fn commit_round(&mut self, player: entities::Controller, commitment: [u8; 32], now: i64) -> ProgramResult {
+        if !self.multi_round {
+            return Err(ErrorCode::InvalidGameSettings.into());
+        }
+        if self.placing_disabled || self.piece_timer.map_or(true, |piece_timer| now > piece_timer) {
+            return Err(ErrorCode::TimeError.into());
+        }
+        match player {
+            entities::Controller::Initializer => self.i_round_commitment = Some(commitment),
+            _ => self.o_round_commitment = Some(commitment),
+        }
+        Ok(())
+    }
+
+    /// Reveal a share of the next round's randomness during the battle. The opposing player has 60 seconds to
+    /// reveal theirs once the first share is in.
+    pub fn reveal_round(&mut self, player: entities::Controller, reveal: &[u8; 32], secret: &[u8; 32], now: i64) -> ProgramResult {
+        let (commitment, has_revealed) = match player {
+            entities::Controller::Initializer => (self.i_round_commitment, self.i_has_revealed),
+            _ => (self.o_round_commitment, self.o_has_revealed),
+        };
+        let valid = commitment.map_or(false, |commitment| validate_reveal(&commitment, reveal, secret));
+        if !self.multi_round || has_revealed || !valid {
+            return Err(ErrorCode::RevealError.into());
+        }
+        let inactivity_timer = Some(now + 60);
+        match player {
+            entities::Controller::Initializer => {
+                self.i_has_revealed = true;
+                self.o_inactivity_timer = inactivity_timer;
+            },
+            _ => {
+                self.o_has_revealed = true;
+                self.i_inactivity_timer = inactivity_timer;
+            },
+        }
+        if self.i_has_revealed && self.o_has_revealed {
+            let mut combined = self.round_randomness.unwrap();
+            combined.iter_mut()
+                .zip(reveal.iter())
+                .for_each(|(x1, x2)| *x1 ^= *x2);
+            self.round_randomness = Some(combined);
+        } else {
+            self.round_randomness = Some(*reveal);
+        }
+        Ok(())
+    }
+
+    /// Buy a card from the shop and put it on the board at a grid square
+    pub fn buy_unit(&mut self, player: entities::Controller, shop_index: u8, grid_x: u16, grid_y: u16) -> ProgramResult {
+        if !self.can_place_on_board(player, grid_x, grid_y, None) {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let piece_limit = self.piece_limit as usize;
+        let ruleset = self.ruleset();
+        let economy = self.get_economy_mut(player).ok_or(ProgramError::InvalidArgument)?;
+        let card = economy.shop.get(shop_index as usize).cloned().flatten().ok_or(ProgramError::InvalidArgument)?;
+        // a full board can still take a unit that merges away immediately
+        if economy.board.len() >= piece_limit && !economy.would_merge(&card) {
+            return Err(ProgramError::InvalidArgument);
+        }
+        if !economy.spend(rounds::card_cost(&card)) {
+            return Err(ErrorCode::GoldError.into());
+        }
+        economy.shop[shop_index as usize] = None;
+        economy.board.push(BoardUnit { card, grid_x, grid_y });
+        economy.merge_units(&ruleset);
+        Ok(())
+    }
+
+    /// Buy a card from the shop onto the bench
+    pub fn buy_unit_to_bench(&mut self, player: entities::Controller, shop_index: u8) -> ProgramResult {
+        let ruleset = self.ruleset();
+        let economy = self.get_economy_mut(player).ok_or(ProgramError::InvalidArgument)?;
+        let card = economy.shop.get(shop_index as usize).cloned().flatten().ok_or(ProgramError::InvalidArgument)?;
+        if !economy.has_bench_space() && !economy.would_merge(&card) {
+            return Err(ProgramError::InvalidArgument);
+        }
+        if !economy.spend(rounds::card_cost(&card)) {
+            return Err(ErrorCode::GoldError.into());
+        }
+        economy.shop[shop_index as usize] = None;
+        economy.bench.push(card);
+        economy.merge_units(&ruleset);
+        Ok(())
+    }
+
+    /// Take a unit off the saved board and put it on the bench
+    pub fn bench_unit(&mut self, player: entities::Controller, board_index: u8) -> ProgramResult {
+        let economy = self.get_economy_mut(player).ok_or(ProgramError::InvalidArgument)?;
+        if board_index as usize >= economy.board.len() || !economy.has_bench_space() {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let unit = economy.board.remove(board_index as usize);
+        economy.bench.push(unit.card);
+        Ok(())
+    }
+
+    /// Put a unit from the bench onto the saved board at a grid square
+    pub fn field_unit(&mut self, player: entities::Controller, bench_index: u8, grid_x: u16, grid_y: u16) -> ProgramResult {
+        if !self.can_place_on_board(player, grid_x, grid_y, None) {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let piece_limit = self.piece_limit as usize;
+        let economy = self.get_economy_mut(player).ok_or(ProgramError::InvalidArgument)?;
+        if bench_index as usize >= economy.bench.len() || economy.board.len() >= piece_limit {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let card = economy.bench.remove(bench_index as usize);
+        economy.board.push(BoardUnit { card, grid_x, grid_y });
+        Ok(())
+    }
+
+    /// Sell a unit off the bench for its cost
+    pub fn sell_bench_unit(&mut self, player: entities::Controller, bench_index: u8) -> ProgramResult {
+        let economy = self.get_economy_mut(player).ok_or(ProgramError::InvalidArgument)?;
+        if bench_index as usize >= economy.bench.len() {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let card = economy.bench.remove(bench_index as usize);
+        economy.gold = economy.gold.saturating_add(rounds::card_cost(&card));
+        Ok(())
+    }
+
+    /// Sell a unit off the board for its cost
+    pub fn sell_unit(&mut self, player: entities::Controller, board_index: u8) -> ProgramResult {
+        let economy = self.get_economy_mut(player).ok_or(ProgramError::InvalidArgument)?;
+        if board_index as usize >= economy.board.len() {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let unit = economy.board.remove(board_index as usize);
+        economy.gold = economy.gold.saturating_add(rounds::card_cost(&unit.card));
+        Ok(())
+    }
+
+    /// Pay to replace the shop with a new one
+    pub fn reroll_shop(&mut self, player: entities::Controller) -> ProgramResult {
+        let economy = self.get_economy_mut(player).ok_or(ProgramError::InvalidArgument)?;
+        if !economy.spend(rounds::REROLL_COST) {
+            return Err(ErrorCode::GoldError.into());
+        }
+        economy.rerolls = economy.rerolls.checked_add(1).ok_or(ProgramError::InvalidArgument)?;
+        self.refresh_shop(player);
+        Ok(())
+    }
+
+    /// Move a unit on the saved board to another grid square
+    pub fn move_unit(&mut self, player: entities::Controller, board_index: u8, grid_x: u16, grid_y: u16) -> ProgramResult {
+        if !self.can_place_on_board(player, grid_x, grid_y, Some(board_index as usize)) {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let economy = self.get_economy_mut(player).ok_or(ProgramError::InvalidArgument)?;
+        let unit = economy.board.get_mut(board_index as usize).ok_or(ProgramError::InvalidArgument)?;
+        unit.grid_x = grid_x;
+        unit.grid_y = grid_y;
+        Ok(())
+    }
+
+    /// Saved boards use the same grid and sides as hidden placement
+    fn can_place_on_board(&self, player: entities::Controller, grid_x: u16, grid_y: u16, ignore: Option<usize>) -> bool {
+        if grid_x > 7 || grid_y > 7 {
+            return false;
+        }
+        match player {
+            entities::Controller::Initializer => grid_y < 4 && self.i_economy.is_free(grid_x, grid_y, ignore),
+            entities::Controller::Opponent => grid_y >= 4 && self.o_economy.is_free(grid_x, grid_y, ignore),
+            _ => false,
+        }
+    }
+
+    /// Replace the entities with both saved boards and start the battle (state 3)
+    pub fn load_boards(&mut self) {
+        self.entities = entities::Entities {
+            all: Vec::new(),
+            counter: 0,
+        };
+        self.projectiles = Projectiles::default();
+        self.tick = 0;
+        for (player, economy) in [(entities::Controller::Initializer, self.i_economy.clone()), (entities::Controller::Opponent, self.o_economy.clone())] {
+            for unit in economy.board {
+                self.entities.create(player, unit.grid_x * 100 + 50, unit.grid_y * 100 + 50, unit.card);
+            }
+        }
+        self.state = 3;
+    }
+
+    /// Once the battle is decided, damage the loser, pay out gold and go to the next shop phase.
+    /// If a player runs out of hp the game stays in state 3 so the winner can claim victory.
+    pub fn finish_round(&mut self, now: i64) -> ProgramResult {
+        // the last round has already been settled
+        self.update_win_condition();
+        if self.win_condition != WinCondition::InProgress {
+            return Err(ErrorCode::ClaimError.into());
+        }
+        // the next shop needs both shares of randomness
+        if !self.i_has_revealed || !self.o_has_revealed {
+            return Err(ErrorCode::RevealError.into());
+        }
+        let result = self.get_battle_result();
+        let survivors = self.entities.all.iter().filter(|entity| entity.state != EntityState::Dead && !entity.is_benched()).count() as u8;
+        let damage = rounds::BASE_ROUND_DAMAGE.saturating_add(survivors);
+        match result {
+            WinCondition::InProgress => {
+                return Err(ErrorCode::ClaimError.into());
+            },
+            WinCondition::Initializer => {
+                self.o_economy.hp = self.o_economy.hp.saturating_sub(damage);
+                self.i_economy.gold = self.i_economy.gold.saturating_add(rounds::GOLD_PER_WIN);
+            },
+            WinCondition::Opponent => {
+                self.i_economy.hp = self.i_economy.hp.saturating_sub(damage);
+                self.o_economy.gold = self.o_economy.gold.saturating_add(rounds::GOLD_PER_WIN);
+            },
+            WinCondition::Tie => {}
+        }
+        self.update_win_condition();
+        if self.win_condition == WinCondition::InProgress {
+            self.start_round(now);
+        }
+        Ok(())
+    }
+
+    /// Retreive a random number derived from the second reveal
+    fn get_random_u8(&mut self) -> u8 {
+        if self.random_calls >= 32 {
+            self.reveal_2 = Some(generate_new_randomness(&self.reveal_2.unwrap()));
+            self.random_calls = 0;
+        }
+        let randomness = self.reveal_2.unwrap();
+        let index = (self.random_calls)as usize;
+        self.random_calls = self.random_calls + 1;
+        return randomness[index];
+    }
+
+    /// Random byte for an entity this tick. Each entity rolls from its own hash, so a roll doesn't depend on how many
+    /// entities rolled before it.
+    fn get_entity_roll(&self, id: u16) -> u8 {
+        hashv(&[&self.reveal_2.unwrap(), &self.tick.to_le_bytes(), &id.to_le_bytes()]).to_bytes()[0]
+    }
+
+    /// Run through one game step (every entity moves).
+    /// A tick resolves in order:
+    ///  - decisions: every living unit queues actions from the board as it was at the start of the tick, walking,
+    ///    starting attacks and casts, and rolling crits for attacks that land. Projectiles move and queue their hits.
+    ///  - trait reactions to the attacks that landed
+    ///  - action phases, see TickPhase. From PHASES_VERSION each phase is finished for every entity before the next
+    ///    starts, and deaths are decided on all the damage a unit took this tick.
+    ///  - events raised while applying actions, with their reactions applied the same way
+    ///  - collision
+    /// Nothing in a tick depends on the order entities were created in, from PHASES_VERSION.
+    pub fn step(&mut self) {
+
+        if self.tick == 0 && self.ruleset_version >= ruleset::SYNERGIES_VERSION {
+            synergies::apply_synergies(&mut self.entities, self.ruleset_version);
+        }
+
+        let scaled_attacks = self.ruleset_version >= ruleset::SPEED_SCALING_VERSION;
+        let traits_active = self.ruleset_version >= ruleset::TRAITS_VERSION;
+        let mut actions: Actions = Actions::new();
+        // projectiles fired this tick start moving next tick
+        let mut fired: Vec<Projectile> = Vec::new();
+        // basic attacks landing this tick, for trait events
+        let mut hits: Vec<Hit> = Vec::new();
+        // loop through entities and queue actions
+        let all_entities = &self.entities.clone();
+        let blockers = pathfinding::get_blockers(all_entities);
+        for entity in &self.entities.all.clone() {
+            if entity.is_benched() || entity.state == EntityState::Dead {
+                continue;
+            }
+            if entity.owner == entities::Controller::Initializer || entity.owner == entities::Controller::Opponent {
+
+                if self.tick == 0 {
+                    if traits_active {
+                        trigger_trait(entity, TraitEvent::BattleStart, all_entities, &mut actions, self.ruleset_version);
+                    } else if entity.special_trait == Some(SpecialTrait::Assassin) {
+                        entity.assassin_hop(&mut actions);
+                    }
+                } else {
+                    for buff in &entity.buffs {
+                        if let EntityBuff::Poison{damage_per_tick, ..} = buff {
+                            actions.add(entity.id, Action::Damage{amount: *damage_per_tick, source: None, crit: false, damage_type: units::DamageType::True});
+                        }
+                    }
+                    if !entity.buffs.is_empty() {
+                        actions.add(entity.id, Action::TickBuffs);
+                    }
+                    if traits_active {
+                        trigger_trait(entity, TraitEvent::Tick, all_entities, &mut actions, self.ruleset_version);
+                    }
+                    if entity.is_stunned() {
+                        continue;
+                    }
+                    match entity.state {
+                        entities::EntityState::Idle | entities::EntityState::Moving{to: _} => {
+                            if self.ruleset_version >= ruleset::ABILITIES_VERSION && entity.ready_to_cast(all_entities) {
+                                entity.start_ability(&mut actions);
+                            } else {
+                                entity.walk_or_aa(&mut actions, all_entities, &blockers, self.ruleset_version);
+                            }
+                        },
+                        entities::EntityState::Ability{progress, cast_on, release_on} => {
+                            let new_progress = progress + 1;
+                            if new_progress == cast_on {
+                                let ability = abilities::get_signature_ability(entity.unit_type).unwrap();
+                                ability.cast(entity, all_entities, &mut actions);
+                            }
+                            if new_progress >= release_on {
+                                actions.add(entity.id, Action::EntityStateChange { state: EntityState::Idle });
+                            } else {
+                                actions.add(entity.id, Action::EntityStateChange {
+                                    state: EntityState::Ability { progress: new_progress, cast_on, release_on }
+                                });
+                            }
+                        },
+                        entities::EntityState::Attack{progress, attack_on, target_id} => {
+                            // scaled attacks progress by the attack speed percent, so attack_on is in hundredths of a tick
+                            let new_progress = if scaled_attacks {
+                                progress.saturating_add(entity.attack_speed_multiplier)
+                            } else {
+                                progress + 1
+                            };
+                            if new_progress >= attack_on {
+                                let stats = entity.stats.as_ref().unwrap();
+
+                                let mut attack_damage = stats.attack_damage;
+                                // calculate crit
+                                let random = if self.ruleset_version >= ruleset::PHASES_VERSION {
+                                    self.get_entity_roll(entity.id)
+                                } else {
+                                    self.get_random_u8()
+                                };
+                                let crit = random < stats.crit_chance;
+                                if crit {
+                                    attack_damage = attack_damage * 2;
+                                }
+                                // attack
+                                match units::get_attack_type(entity.unit_type) {
+                                    units::AttackType::Melee => {
+                                        actions.add(target_id, Action::Damage{amount: attack_damage, source: Some(entity.id), crit, damage_type: units::DamageType::Physical});
+                                        hits.push(Hit { attacker_id: entity.id, target_id, damage: attack_damage });
+                                    },
+                                    units::AttackType::Ranged{speed} => {
+                                        fired.push(Projectile {
+                                            owner: entity.owner,
+                                            source: entity.id,
+                                            position: entity.position,
+                                            target: ProjectileTarget::Homing{target_id},
+                                            speed,
+                                            damage: attack_damage,
+                                            crit,
+                                        });
+                                    },
+                                }
+                                actions.add(entity.id, Action::GainMana{amount: abilities::MANA_PER_ATTACK});
+                                actions.add(entity.id, Action::EntityStateChange { state: EntityState::Idle });
+                            } else {
+                                actions.add(entity.id, Action::EntityStateChange { 
+                                    state: EntityState::Attack { progress: new_progress, attack_on, target_id }
+                                });
+                            }
+                        },
+                        entities::EntityState::Airborne { progress, finish_on, to } => {
+                            let new_progress = progress + 1;
+                            if new_progress >= finish_on {
+                                actions.add(entity.id, Action::Move { to });
+                                actions.add(entity.id, Action::EntityStateChange { state: EntityState::Idle });
+                            } else {
+                                let distance = entity.position.distance(&to);
+                                let move_to = entity.position.move_towards(&to, distance, distance / finish_on);
+                                actions.add(entity.id, Action::Move { to: move_to});
+                                actions.add(entity.id, Action::EntityStateChange { 
+                                    state: EntityState::Airborne { progress: new_progress, finish_on, to }
+                                });
+                            }
+                        }
+                        _other=>{}
+                    }
+                }
+            }
+        };
+
+        hits.extend(self.projectiles.step(all_entities, &mut actions));
+        for projectile in fired {
+            self.projectiles.add(projectile);
+        }
+        if traits_active {
+            for hit in &hits {
+                for (id, event) in [
+                    (hit.attacker_id, TraitEvent::Attack{target_id: hit.target_id, damage: hit.damage}),
+                    (hit.target_id, TraitEvent::Hit{attacker_id: hit.attacker_id, damage: hit.damage}),
+                ] {
+                    match all_entities.get_by_id(id) {
+                        Some(entity) if entity.state != EntityState::Dead => trigger_trait(entity, event, all_entities, &mut actions, self.ruleset_version),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        let alive: Vec<u16> = self.entities.all.iter()
+            .filter(|entity| !entity.is_benched() && entity.state != EntityState::Dead)
+            .map(|entity| entity.id)
+            .collect();
+        let mut events = Events::default();
+        self.apply_actions(&actions, &mut events);
+        if self.ruleset_version >= ruleset::EVENTS_VERSION {
+            self.process_events(all_entities, events);
+        } else if traits_active {
+            self.resolve_deaths(all_entities, &alive, &hits);
+        }
+        if self.ruleset_version >= ruleset::COLLISION_VERSION {
+            collision::separate(&mut self.entities, self.ruleset_version);
+        }
+        self.tick = self.tick + 1;
+    }
+
+    /// Apply queued actions to every entity. From PHASES_VERSION they go through the phases in TickPhase; before that,
+    /// each entity's actions were applied in the order they were queued, one entity after another.
+    /// Damage raises events for the follow-up phase.
+    fn apply_actions(&mut self, actions: &Actions, events: &mut Events) {
+        if self.ruleset_version >= ruleset::PHASES_VERSION {
+            self.apply_phases(actions, events);
+            return;
+        }
+        for entity in &mut self.entities.all {
+            if let Some(actions_for_id) = actions.get_actions_by_id(&entity.id) {
+                for action in actions_for_id {
+                    apply_action(entity, action, self.ruleset_version, events);
+                }
+            }
+        }
+    }
+
+    /// Apply actions phase by phase. Every hit on a unit counts, even ones queued after a hit that already killed it,
+    /// so the events raised don't depend on which attacker was queued first:
+    ///  - damage dealt is shared out between the hits by size, out of what got through shields and health
+    ///  - the kill goes to whoever dealt the most damage to the unit this tick. Tied killers all get the kill,
+    ///    and the death has no single killer.
+    fn apply_phases(&mut self, actions: &Actions, events: &mut Events) {
+        let ruleset_version = self.ruleset_version;
+        let queued = |id: u16, phase: TickPhase| {
+            let mut queued: Vec<&Action> = actions.get_actions_by_id(&id)
+                .map_or(Vec::new(), |actions_for_id| actions_for_id.iter().filter(|action| action.phase().0 == phase).collect());
+            // stable, so actions of the same rank keep their order
+            queued.sort_by_key(|action| action.phase().1);
+            queued
+        };
+
+        for entity in &mut self.entities.all {
+            for action in queued(entity.id, TickPhase::Movement) {
+                apply_action(entity, action, ruleset_version, events);
+            }
+        }
+
+        // (victim, damage by source) for units that died this tick
+        let mut deaths: Vec<(u16, BTreeMap<u16, u32>)> = Vec::new();
+        for entity in &mut self.entities.all {
+            let damage_phase = queued(entity.id, TickPhase::Damage);
+            let was_alive = entity.state != EntityState::Dead;
+            let health = entity.health;
+            let mut hits: Vec<(Option<u16>, u16, bool)> = Vec::new();
+            for action in &damage_phase {
+                if let Action::Damage{amount, source, crit, damage_type} = **action {
+                    if !was_alive {
+                        continue;
+                    }
+                    let amount = if ruleset_version >= ruleset::MITIGATION_VERSION {
+                        entity.mitigate(amount, damage_type)
+                    } else {
+                        amount
+                    };
+                    if entity.state != EntityState::Dead {
+                        entity.take_damage(amount);
+                    }
+                    hits.push((source, amount, crit));
+                }
+            }
+            let total: u32 = hits.iter().map(|(_, amount, _)| *amount as u32).sum();
+            let lost = (health - entity.health) as u32;
+            let mut by_source: BTreeMap<u16, u32> = BTreeMap::new();
+            for (source, amount, crit) in hits {
+                if let Some(source_id) = source {
+                    if crit {
+                        events.push(GameEvent::Crit{source_id, target_id: entity.id, amount});
+                    }
+                    let dealt = if total > 0 { amount as u32 * lost / total } else { 0 };
+                    events.push(GameEvent::DamageDealt{source_id, target_id: entity.id, amount: dealt as u16});
+                    *by_source.entry(source_id).or_insert(0) += amount as u32;
+                }
+            }
+            if was_alive && entity.state == EntityState::Dead {
+                deaths.push((entity.id, by_source));
+            }
+            for action in damage_phase {
+                if let Action::Heal{..} = action {
+                    apply_action(entity, action, ruleset_version, events);
+                }
+            }
+        }
+
+        for (victim_id, by_source) in deaths {
+            let most = by_source.values().copied().max().unwrap_or(0);
+            let killers: Vec<u16> = by_source.into_iter()
+                .filter(|(_, amount)| *amount == most)
+                .map(|(source_id, _)| source_id)
+                .collect();
+            let killer_id = if killers.len() == 1 { Some(killers[0]) } else { None };
+            events.push(GameEvent::Death{entity_id: victim_id, killer_id});
+            for killer_id in killers {
+                events.push(GameEvent::Kill{entity_id: killer_id, victim_id});
+            }
+        }
+
+        for entity in &mut self.entities.all {
+            for action in queued(entity.id, TickPhase::State) {
+                apply_action(entity, action, ruleset_version, events);
+            }
+        }
+    }
+
+    /// Handle events raised while applying actions. Reactions are applied straight away and may raise events of their own,
+    /// which are handled in the next round, up to MAX_EVENT_ROUNDS rounds a tick. Anything left after that is dropped.
+    /// `before` is the board at the start of the tick, so units that died are seen as they were before the killing blow.
+    fn process_events(&mut self, before: &Entities, mut events: Events) {
+        for _ in 0..MAX_EVENT_ROUNDS {
+            if events.is_empty() {
+                break;
+            }
+            let board = self.entities.clone();
+            let mut actions = Actions::new();
+            while let Some(event) = events.pop() {
+                handle_event(event, before, &board, &mut actions, self.ruleset_version);
+            }
+            self.apply_actions(&actions, &mut events);
+        }
+        debug_assert!(events.is_empty(), "events left after {} rounds", MAX_EVENT_ROUNDS);
+    }
+
+    /// Raise death and kill trait events for units that died this tick, and apply what the traits queue in response.
+    /// How deaths were resolved between TRAITS_VERSION and EVENTS_VERSION.
+    /// `before` is the board at the start of the tick, so units that died are seen as they were before the killing blow.
+    /// Every unit that landed a basic attack on a victim this tick counts as a killer.
+    fn resolve_deaths(&mut self, before: &Entities, alive: &Vec<u16>, hits: &Vec<Hit>) {
+        let board = self.entities.clone();
+        let mut actions = Actions::new();
+        for victim_id in alive {
+            if board.get_by_id(*victim_id).map_or(true, |entity| entity.state != EntityState::Dead) {
+                continue;
+            }
+            if let Some(victim) = before.get_by_id(*victim_id) {
+                trigger_trait(victim, TraitEvent::Death, &board, &mut actions, self.ruleset_version);
+            }
+            let mut killers: Vec<u16> = hits.iter()
+                .filter(|hit| hit.target_id == *victim_id)
+                .map(|hit| hit.attacker_id)
+                .collect();
+            killers.sort();
+            killers.dedup();
+            for killer_id in killers {
+                match board.get_by_id(killer_id) {
+                    Some(killer) if killer.state != EntityState::Dead => {
+                        trigger_trait(killer, TraitEvent::Kill{victim_id: *victim_id}, &board, &mut actions, self.ruleset_version);
+                    },
+                    _ => {}
+                }
+            }
+        }
+        self.apply_actions(&actions, &mut Events::default());
+    }
+    pub fn get_player_type(&self, burner_wallet: Pubkey) -> entities::Controller {
+        if self.i_burner == burner_wallet {
+            entities::Controller::Initializer
+        } else {
+            entities::Controller::Opponent
+        }
+    }
+
+    pub fn lock_in_player(&mut self, player_type: entities::Controller) -> ProgramResult {
+        match player_type {
+            entities::Controller::Initializer => {
+                self.i_locked_in = true;
+            }
+            entities::Controller::Opponent => {
+                self.o_locked_in = true;
+            }
+            _ => {
+                return Err(ProgramError::InvalidArgument);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn both_players_locked(&self) -> bool {
+        self.i_locked_in && self.o_locked_in
+    }
+}
+
+pub fn validate_reveal(stored_hash: &[u8; 32], reveal: &[u8; 32], secret: &[u8; 32]) -> bool {
+    let hash = extend_and_hash(&Hash::new_from_array(*reveal), secret);
+    if Hash::new_from_array(*stored_hash) != hash {
+        return false;
+    }
+    return true;
+}
+
+pub fn generate_new_randomness(stored_hash: &[u8; 32]) -> [u8; 32] {
+    hash(stored_hash).to_bytes()
+}
+
+/// Let traits and abilities react to an event. Reactions only go to living units, except for the dying unit's own death.
+fn handle_event(event: GameEvent, before: &Entities, board: &Entities, actions: &mut Actions, ruleset_version: u16) {
+    let living = |id: u16| board.get_by_id(id).filter(|entity| entity.state != EntityState::Dead);
+    match event {
+        GameEvent::DamageDealt{source_id, target_id, amount} => {
+            if let Some(source) = living(source_id) {
+                let healing = (amount as u32 * source.lifesteal as u32 / 100) as u16;
+                if ruleset_version >= ruleset::HEALING_VERSION && healing > 0 {
+                    actions.add(source_id, Action::Heal{amount: healing});
+                }
+                trigger_trait(source, TraitEvent::DamageDealt{target_id, amount}, board, actions, ruleset_version);
+            }
+        },
+        GameEvent::Crit{source_id, target_id, amount} => {
+            if let Some(source) = living(source_id) {
+                trigger_trait(source, TraitEvent::Crit{target_id, damage: amount}, board, actions, ruleset_version);
+            }
+        },
+        GameEvent::Death{entity_id, ..} => {
+            if let Some(entity) = before.get_by_id(entity_id) {
+                trigger_trait(entity, TraitEvent::Death, board, actions, ruleset_version);
+            }
+        },
+        GameEvent::Kill{entity_id, victim_id} => {
+            if let Some(killer) = living(entity_id) {
+                actions.add(killer.id, Action::GainMana{amount: abilities::MANA_PER_KILL});
+                trigger_trait(killer, TraitEvent::Kill{victim_id}, board, actions, ruleset_version);
+            }
+        },
+    }
+}
+
+/// Apply one action to the entity it was queued for. Damage raises events for the follow-up phase.
+fn apply_action(entity: &mut entities::Entity, action: &Action, ruleset_version: u16, events: &mut Events) {
+    match *action {
+        Action::Damage{amount, source, crit, damage_type} => {
+            if entity.state != EntityState::Dead {
+                // crits were doubled when queued, so they're mitigated like any other hit
+                let amount = if ruleset_version >= ruleset::MITIGATION_VERSION {
+                    entity.mitigate(amount, damage_type)
+                } else {
+                    amount
+                };
+                let health = entity.health;
+                entity.take_damage(amount);
+                if let Some(source_id) = source {
+                    if crit {
+                        events.push(GameEvent::Crit{source_id, target_id: entity.id, amount});
+                    }
+                    events.push(GameEvent::DamageDealt{source_id, target_id: entity.id, amount: health - entity.health});
+                }
+                if entity.state == EntityState::Dead {
+                    events.push(GameEvent::Death{entity_id: entity.id, killer_id: source});
+                    if let Some(killer_id) = source {
+                        events.push(GameEvent::Kill{entity_id: killer_id, victim_id: entity.id});
+                    }
+                }
+            }
+        },
+        Action::Heal{amount} => {
+            entity.heal(amount, ruleset_version);
+        },
+        Action::ApplyBuff{buff} => {
+            entity.apply_buff(buff, ruleset_version);
+        },
+        Action::GainMana{amount} => {
+            entity.mana = entity.mana.saturating_add(amount).min(abilities::MAX_MANA);
+        },
+        Action::ResetMana => {
+            entity.mana = 0;
+        },
+        Action::TickBuffs => {
+            entity.tick_buffs();
+        },
+        Action::EntityStateChange { state } => {
+            // stunned units stay interrupted even if they queued a state change this tick
+            if entity.state != EntityState::Dead && !entity.is_stunned() {
+                entity.state = state;
+            }
+        },
+        Action::Move { to } => {
+            entity.position = to;
+        },
+        Action::Target { target_id } => {
+            entity.target = target_id;
+        }
+    }
+}
+
+/// Pass an event to the entity's special trait, if it has one
+fn trigger_trait(entity: &entities::Entity, event: TraitEvent, all_entities: &Entities, actions: &mut Actions, ruleset_version: u16) {
+    if let Some(special_trait) = entity.special_trait {
+        traits::trigger(special_trait, event, entity, all_entities, actions, ruleset_version);
+    }
+}
+
+/// Get element from array based on random, which is in [0,256).
+fn get_from_p_array<T: Clone>(array: &[(T, u8)], random: u8) -> T {
+    let total_p = array.iter().fold(0u32, |acc, (_, p)| acc + *p as u32);
+    // random / 256 * total_p < running_total, compared exactly
+    let mut running_total = 0u32;
+    for (item, weight) in array.iter() {
+        running_total += *weight as u32;
+        if fixed::ratio_less_than(random as u32 * total_p, 256, running_total, 1) {
+            return item.clone();
+        }
+    }
+    panic!("random {} is out of range", random);
+}
+
+/// Merge groups of three hand positions in order. The first position of each group becomes the merged card
+/// and the other two are used up, so a later group can merge 2 star cards into a 3 star.
+/// Returns the used up positions, or None if any merge is invalid.
+fn apply_merges(hand: &mut Vec<units::Card>, merges: &Vec<[u8; 3]>, unavailable: &Vec<u8>, ruleset: &Ruleset) -> Option<Vec<u8>> {
+    let mut consumed: Vec<u8> = Vec::new();
+    for merge in merges {
+        let [a, b, c] = *merge;
+        if a == b || b == c || a == c {
+            return None;
+        }
+        for position in merge {
+            if *position as usize >= hand.len() || consumed.contains(position) || unavailable.contains(position) {
+                return None;
+            }
+        }
+        hand[a as usize] = units::merge_cards(&hand[a as usize], &hand[b as usize], &hand[c as usize], ruleset)?;
+        consumed.push(b);
+        consumed.push(c);
+    }
+    Some(consumed)
+}
+
+/// XOR two sources of randomness together
+fn combine_randomness(randomness1: &[u8; 32], randomness2: &[u8; 32]) -> [u8; 32] {
+    let mut reveal = randomness1.clone();
+    reveal.iter_mut()
+        .zip(randomness2.iter())
+        .for_each(|(x1, x2)| *x1 ^= *x2);
+    reveal
+}
+
+/// Draw HAND_SIZE cards by shuffling a constructed deck with the randomness of first reveal and second commit.
+/// Only the first HAND_SIZE positions of the shuffle are needed, so this is a partial Fisher-Yates.
+/// Client side, but verified on chain.
+pub fn draw_hand_from_deck(hand_size: u8, randomness1: &[u8; 32], randomness2: &[u8; 32], deck: &Vec<DeckCard>, ruleset: &Ruleset) -> Vec<units::Card> {
+    msg!("drawing from deck");
+    let mut order: Vec<usize> = (0..deck.len()).collect();
+    let mut randomness = generate_new_randomness(&combine_randomness(randomness1, randomness2));
+    let mut random_index = 0;
+    for i in 0..(hand_size as usize).min(deck.len()) {
+        if random_index + 2 > randomness.len() {
+            randomness = generate_new_randomness(&randomness);
+            random_index = 0;
+        }
+        let random = u16::from_le_bytes([randomness[random_index], randomness[random_index + 1]]) as usize;
+        random_index += 2;
+        let j = i + random % (deck.len() - i);
+        order.swap(i, j);
+    }
+    order.iter()
+        .take(hand_size as usize)
+        .map(|i| deck[*i].to_card(ruleset).unwrap())
+        .collect()
+}
+
+/// Draw HAND_SIZE cards from deck using randomness of first reveal and second commit. Client side, but verified on chain.
+pub fn draw_hand(hand_size: u8, randomness1: &[u8; 32], randomness2: &[u8; 32], ruleset: &Ruleset) -> Vec<units::Card> {
+    // XOR randomness together
+    msg!("drawing");
+    let reveal = combine_randomness(randomness1, randomness2);
+
+    let mut result: Vec<units::Card> = Vec::new();
+
+    // Array of unit type and the relative probability
+    let mut deck = vec![
+        (units::UnitType::Wolf, 1 as u8),
+        (units::UnitType::Bear, 1),
+        (units::UnitType::Bull, 1),
+    ];
+    if ruleset.version >= ruleset::RANGED_UNITS_VERSION {
+        deck.push((units::UnitType::Hawk, 1));
+    }
+
+    let rarities = [
+        (units::Rarity::Common, 150 as u8),
         (units::Rarity::Uncommon, 65),
         (units::Rarity::Rare, 25),
         (units::Rarity::Epic, 10),
@@ -496,10 +1729,23 @@ pub fn draw_hand(hand_size: u8, randomness1: &[u8; 32], randomness2: &[u8; 32])
         (units::Rarity::Mythic, 1),
     ];
 
-    let special_traits = [
-        (Some(units::SpecialTrait::Assassin), 1 as u8),
-        (None, 4),
-    ];
+    // one in five cards has a trait, spread evenly over the traits in the ruleset
+    let special_traits = if ruleset.version >= ruleset::TRAITS_VERSION {
+        vec![
+            (Some(units::SpecialTrait::Assassin), 1 as u8),
+            (Some(units::SpecialTrait::Berserker), 1),
+            (Some(units::SpecialTrait::Guardian), 1),
+            (Some(units::SpecialTrait::Vampiric), 1),
+            (Some(units::SpecialTrait::Thorns), 1),
+            (Some(units::SpecialTrait::Swift), 1),
+            (None, 24),
+        ]
+    } else {
+        vec![
+            (Some(units::SpecialTrait::Assassin), 1 as u8),
+            (None, 4),
+        ]
+    };
 
     let mut randomness = generate_new_randomness(&reveal);
     for _ in 0..hand_size {
@@ -509,12 +1755,13 @@ pub fn draw_hand(hand_size: u8, randomness1: &[u8; 32], randomness2: &[u8; 32])
         let unit_type = get_from_p_array(&deck, rnd_unit_type);
         let rarity = get_from_p_array(&rarities, rnd_rarity);
         let special_trait = get_from_p_array(&special_traits, rnd_special_trait);
-        let stats = units::get_baseline_unit_stats(unit_type, rarity).unwrap();
+        let stats = ruleset.get_unit_stats(unit_type, rarity, 1).unwrap();
         result.push(units::Card {
             unit_type,
             stats,
             rarity,
-            special_trait
+            special_trait,
+            star_level: 1,
         });
         randomness = generate_new_randomness(&randomness); 
     }
@@ -524,6 +1771,7 @@ pub fn draw_hand(hand_size: u8, randomness1: &[u8; 32], randomness2: &[u8; 32])
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::state::{merkle, registry, test_vectors};
 
     #[test]
     fn lock_in_test() {
@@ -549,4 +1797,753 @@ mod tests {
         assert_eq!(result, None);
         assert_eq!(test_game.entities.all.len(), 0);
     }
+
+    #[test]
+    fn decks_are_selected_before_any_reveal() {
+        let mut test_game = Game::new_client();
+        test_game.hand_mode = HandMode::Deck;
+        test_game.initializer = Pubkey::new_unique();
+        test_game.opponent = Pubkey::new_unique();
+        let cards: Vec<DeckCard> = (0..test_game.hand_size)
+            .map(|_| DeckCard { unit_type: units::UnitType::Wolf, rarity: units::Rarity::Common, special_trait: None })
+            .collect();
+        let i_deck = Deck { owner: test_game.initializer, cards: cards.clone() };
+        let o_deck = Deck { owner: test_game.opponent, cards };
+
+        // someone else's deck can't be used
+        assert!(test_game.select_deck(entities::Controller::Initializer, &o_deck).is_err());
+        assert!(test_game.select_deck(entities::Controller::Initializer, &i_deck).is_ok());
+        // nor swapped once selected
+        assert!(test_game.select_deck(entities::Controller::Initializer, &i_deck).is_err());
+        // no one reveals until both decks are in, so the opponent still gets to pick theirs
+        assert!(!test_game.hand_source_locked(entities::Controller::Initializer));
+        assert!(test_game.select_deck(entities::Controller::Opponent, &o_deck).is_ok());
+        assert!(test_game.hand_source_locked(entities::Controller::Initializer));
+        assert!(test_game.hand_source_locked(entities::Controller::Opponent));
+        assert_eq!(test_game.o_deck_hash, Some(o_deck.content_hash()));
+    }
+
+    #[test]
+    fn draw_hand_from_deck_draws_each_card_once() {
+        let rarities = [units::Rarity::Common, units::Rarity::Uncommon, units::Rarity::Rare, units::Rarity::Epic];
+        let mut deck = Vec::new();
+        for unit_type in [units::UnitType::Wolf, units::UnitType::Bear, units::UnitType::Bull] {
+            for rarity in rarities {
+                deck.push(DeckCard { unit_type, rarity, special_trait: None });
+            }
+        }
+        let hand = draw_hand_from_deck(8, &[3; 32], &[7; 32], &deck, &Ruleset::current());
+        assert_eq!(hand.len(), 8);
+        for (i, card) in hand.iter().enumerate() {
+            assert!(deck.iter().any(|c| c.to_card(&Ruleset::current()).as_ref() == Some(card)));
+            assert!(!hand[..i].contains(card), "card drawn twice");
+        }
+        assert_eq!(hand, draw_hand_from_deck(8, &[3; 32], &[7; 32], &deck, &Ruleset::current()));
+    }
+
+    #[test]
+    fn mulligan_replacements_need_discards() {
+        let mut test_game = Game::new_client();
+        test_game.hand_size = 8;
+        test_game.piece_limit = 5;
+        test_game.reveal_1 = Some([1; 32]);
+        let reveal_2 = [2; 32];
+        // place the original card at position 0 and the first replacement
+        test_game.place_piece_hidden(entities::Controller::Initializer, 1, 1, 0).unwrap();
+        test_game.place_piece_hidden(entities::Controller::Initializer, 2, 1, 8).unwrap();
+        assert_eq!(test_game.place_piece_hidden(entities::Controller::Initializer, 3, 1, 10), None);
+
+        assert!(test_game.reveal_hidden_pieces(entities::Controller::Initializer, &reveal_2, &HandAdjustments::default()).is_err());
+        // can't discard a card that was placed
+        assert!(test_game.reveal_hidden_pieces(entities::Controller::Initializer, &reveal_2, &HandAdjustments { discards: vec![0], merges: Vec::new() }).is_err());
+        assert!(test_game.reveal_hidden_pieces(entities::Controller::Initializer, &reveal_2, &HandAdjustments { discards: vec![3, 3], merges: Vec::new() }).is_err());
+
+        test_game.reveal_hidden_pieces(entities::Controller::Initializer, &reveal_2, &HandAdjustments { discards: vec![3], merges: Vec::new() }).unwrap();
+        let hand = draw_hand(10, &[1; 32], &reveal_2, &Ruleset::current());
+        assert_eq!(test_game.entities.all[1].unit_type, hand[8].unit_type);
+        assert_eq!(test_game.entities.all[1].stats, Some(hand[8].stats.clone()));
+    }
+
+    #[test]
+    fn adjustments_must_match_placement_commitment() {
+        let mut test_game = Game::new_client();
+        test_game.piece_timer = Some(100);
+        let secret = [9; 32];
+        let committed = HandAdjustments { discards: vec![3], merges: Vec::new() };
+        // nothing to commit to for a hand left as drawn
+        assert!(test_game.validate_adjustments(entities::Controller::Initializer, &HandAdjustments::default(), &secret));
+        assert!(!test_game.validate_adjustments(entities::Controller::Initializer, &committed, &secret));
+
+        test_game.commit_adjustments(entities::Controller::Initializer, committed.commitment(&secret), 50).unwrap();
+        assert!(test_game.validate_adjustments(entities::Controller::Initializer, &committed, &secret));
+        // a different discard, a different secret, or the other player's slot don't match
+        assert!(!test_game.validate_adjustments(entities::Controller::Initializer, &HandAdjustments { discards: vec![4], merges: Vec::new() }, &secret));
+        assert!(!test_game.validate_adjustments(entities::Controller::Initializer, &committed, &[8; 32]));
+        assert!(!test_game.validate_adjustments(entities::Controller::Opponent, &committed, &secret));
+
+        // can't commit once placement is over
+        assert!(test_game.commit_adjustments(entities::Controller::Opponent, committed.commitment(&secret), 101).is_err());
+    }
+
+    #[test]
+    fn multi_round_economy() {
+        let mut test_game = Game::new_client();
+        test_game.multi_round = true;
+        test_game.piece_limit = 5;
+        test_game.reveal_1 = Some([9; 32]);
+        test_game.reveal_2 = Some([5; 32]);
+        test_game.start_round(0);
+        assert_eq!(test_game.round, 1);
+        assert_eq!(test_game.i_economy.shop.len(), rounds::SHOP_SIZE as usize);
+
+        let shop = test_game.i_economy.shop.clone();
+        test_game.buy_unit(entities::Controller::Initializer, 0, 3, 1).unwrap();
+        assert_eq!(test_game.i_economy.gold, rounds::STARTING_GOLD - rounds::card_cost(shop[0].as_ref().unwrap()));
+        // slot is empty now, and the opponent's side is off limits
+        assert!(test_game.buy_unit(entities::Controller::Initializer, 0, 4, 1).is_err());
+        assert!(test_game.buy_unit(entities::Controller::Initializer, 1, 3, 5).is_err());
+
+        test_game.reroll_shop(entities::Controller::Initializer).unwrap();
+        assert_ne!(test_game.i_economy.shop, shop);
+
+        // opponent has no units, so initializer wins the round
+        test_game.load_boards();
+        assert_eq!(test_game.entities.all.len(), 1);
+        let hp = test_game.o_economy.hp;
+        // the next round can't start until both players reveal their share of its randomness
+        assert!(test_game.finish_round(0).is_err());
+        let commitment = |reveal: [u8; 32]| extend_and_hash(&Hash::new_from_array(reveal), &[1; 32]).to_bytes();
+        test_game.i_round_commitment = Some(commitment([3; 32]));
+        test_game.o_round_commitment = Some(commitment([4; 32]));
+        assert!(test_game.reveal_round(entities::Controller::Initializer, &[4; 32], &[1; 32], 0).is_err());
+        test_game.reveal_round(entities::Controller::Initializer, &[3; 32], &[1; 32], 0).unwrap();
+        test_game.reveal_round(entities::Controller::Opponent, &[4; 32], &[1; 32], 0).unwrap();
+        assert_eq!(test_game.round_randomness, Some([3 ^ 4; 32]));
+        test_game.finish_round(0).unwrap();
+        assert_eq!(test_game.o_economy.hp, hp - rounds::BASE_ROUND_DAMAGE - 1);
+        assert_eq!(test_game.round, 2);
+        assert_eq!(test_game.state, 2);
+        assert_eq!(test_game.win_condition, WinCondition::InProgress);
+        assert_eq!(test_game.i_economy.board.len(), 1);
+        // the new shop comes from the revealed randomness, not just the round number
+        let expected = draw_hand(rounds::SHOP_SIZE, &[9; 32], &rounds::shop_randomness(&[3 ^ 4; 32], entities::Controller::Initializer, 2, 0), &test_game.ruleset());
+        assert_eq!(test_game.i_economy.shop, expected.into_iter().map(Some).collect::<Vec<_>>());
+        assert_eq!(test_game.i_round_commitment, None);
+    }
+
+    #[test]
+    fn merges_upgrade_the_first_position() {
+        let mut test_game = Game::new_client();
+        test_game.hand_size = 8;
+        test_game.piece_limit = 5;
+        test_game.reveal_1 = Some([1; 32]);
+        let reveal_2 = [2; 32];
+        let hand = draw_hand(8, &[1; 32], &reveal_2, &Ruleset::current());
+        // find three matching cards in the hand
+        let matching: Vec<u8> = (0..8u8).filter(|i| hand[*i as usize].merges_with(&hand[0])).collect();
+        assert!(matching.len() >= 3, "test hand should have three matching cards");
+        let merge = [matching[0], matching[1], matching[2]];
+
+        test_game.place_piece_hidden(entities::Controller::Initializer, 1, 1, merge[1]).unwrap();
+        let adjustments = HandAdjustments { discards: Vec::new(), merges: vec![merge] };
+        // a used up card can't be on the board
+        assert!(test_game.reveal_hidden_pieces(entities::Controller::Initializer, &reveal_2, &adjustments).is_err());
+
+        test_game.remove_piece_hidden(entities::Controller::Initializer, merge[1]).unwrap();
+        test_game.place_piece_hidden(entities::Controller::Initializer, 1, 1, merge[0]).unwrap();
+        test_game.reveal_hidden_pieces(entities::Controller::Initializer, &reveal_2, &adjustments).unwrap();
+        let entity = &test_game.entities.all[0];
+        assert_eq!(entity.star_level, 2);
+        assert_eq!(entity.stats, units::get_star_unit_stats(ruleset::CURRENT_RULESET_VERSION, hand[0].unit_type, hand[0].rarity, 2));
+    }
+
+    #[test]
+    fn benched_pieces_skip_piece_limit() {
+        let mut test_game = Game::new_client();
+        test_game.hand_size = 8;
+        test_game.piece_limit = 1;
+        test_game.place_piece_hidden(entities::Controller::Initializer, 1, 1, 0).unwrap();
+        assert_eq!(test_game.place_piece_hidden(entities::Controller::Initializer, 2, 1, 1), None);
+
+        test_game.bench_piece_hidden(entities::Controller::Initializer, 0, 1).unwrap();
+        assert_eq!(test_game.bench_piece_hidden(entities::Controller::Initializer, 0, 2), None);
+        assert_eq!(test_game.entities.count_for_controller(entities::Controller::Initializer), 1);
+
+        // swap the board piece with the benched one
+        test_game.swap_piece_hidden(entities::Controller::Initializer, 0, 1).unwrap();
+        assert_eq!(test_game.entities.all[0].bench_slot, Some(0));
+        assert_eq!(test_game.entities.all[1].bench_slot, None);
+        assert_eq!(test_game.entities.all[1].position, utils::Location { x: 150, y: 150 });
+
+        // the board is full so the benched piece can't come back in
+        assert_eq!(test_game.move_piece_hidden(entities::Controller::Initializer, 3, 1, 0), None);
+    }
+
+    #[test]
+    fn old_rulesets_skip_synergies() {
+        let card = |unit_type| units::Card {
+            stats: units::get_baseline_unit_stats(1, unit_type, units::Rarity::Common).unwrap(),
+            ..units::test_card(unit_type, None)
+        };
+        let mut games = Vec::new();
+        for ruleset_version in [1, ruleset::SYNERGIES_VERSION] {
+            let mut test_game = Game::new_client();
+            test_game.ruleset_version = ruleset_version;
+            test_game.entities.create(entities::Controller::Initializer, 100, 100, card(units::UnitType::Bear));
+            test_game.entities.create(entities::Controller::Initializer, 300, 100, card(units::UnitType::Bull));
+            test_game.step();
+            games.push(test_game);
+        }
+        let bear_stats = units::get_baseline_unit_stats(1, units::UnitType::Bear, units::Rarity::Common).unwrap();
+        assert_eq!(games[0].entities.all[0].health, bear_stats.starting_health);
+        assert!(games[1].entities.all[0].health > bear_stats.starting_health);
+    }
+
+    #[test]
+    fn full_mana_casts_signature_ability() {
+        let mut test_game = Game::new_client();
+        let bear = test_game.entities.create(entities::Controller::Initializer, 400, 100, units::test_card(units::UnitType::Bear, None));
+        let wolf = test_game.entities.create(entities::Controller::Opponent, 400, 340, units::test_card(units::UnitType::Wolf, None));
+        test_game.entities.get_by_id_mut(bear).unwrap().mana = abilities::MAX_MANA;
+
+        // tick 0 is battle start, then the bear starts casting and taunts on cast_on
+        let (cast_on, _) = abilities::Ability::Taunt.timing();
+        for _ in 0..cast_on + 2 {
+            test_game.step();
+        }
+        let bear_entity = test_game.entities.get_by_id(bear).unwrap();
+        assert!(bear_entity.get_shield() > 0);
+        assert!(bear_entity.mana < abilities::MAX_MANA);
+        assert_eq!(test_game.entities.get_by_id(wolf).unwrap().target, Some(bear));
+    }
+
+    #[test]
+    fn ranged_attacks_fire_projectiles() {
+        let mut test_game = Game::new_client();
+        test_game.reveal_2 = Some([255; 32]); // no crits
+        test_game.entities.create(entities::Controller::Initializer, 400, 100, units::test_card(units::UnitType::Hawk, None));
+        let bear = test_game.entities.create(entities::Controller::Opponent, 400, 450, units::test_card(units::UnitType::Bear, None));
+        let starting_health = test_game.entities.get_by_id(bear).unwrap().health;
+
+        let mut fired = false;
+        for _ in 0..20 {
+            test_game.step();
+            fired |= !test_game.projectiles.all.is_empty();
+        }
+        assert!(fired);
+        assert!(test_game.entities.get_by_id(bear).unwrap().health < starting_health);
+    }
+
+    #[test]
+    fn buffs_tick_down_and_change_behaviour() {
+        let card = units::test_card(units::UnitType::Wolf, None);
+        let mut test_game = Game::new_client();
+        let wolf = test_game.entities.create(entities::Controller::Initializer, 400, 100, card.clone());
+        test_game.entities.create(entities::Controller::Opponent, 400, 700, card);
+
+        let entity = test_game.entities.get_by_id_mut(wolf).unwrap();
+        entity.apply_buff(EntityBuff::Slow { duration: 2, percent: 50 }, ruleset::CURRENT_RULESET_VERSION);
+        entity.apply_buff(EntityBuff::Shield { duration: 5, amount: 10 }, ruleset::CURRENT_RULESET_VERSION);
+        entity.apply_buff(EntityBuff::Untargetable { duration: 2 }, ruleset::CURRENT_RULESET_VERSION);
+        assert_eq!(entity.speed_multiplier, 50);
+        entity.take_damage(4);
+        assert_eq!(entity.get_shield(), 6);
+        let health = entity.health;
+        assert!(test_game.entities.find_closest_entity(&utils::Location { x: 400, y: 700 }, entities::Controller::Initializer).is_none());
+
+        // tick 0, then two ticks of buffs
+        test_game.step();
+        test_game.step();
+        test_game.step();
+        let entity = test_game.entities.get_by_id(wolf).unwrap();
+        assert_eq!(entity.speed_multiplier, 100);
+        assert!(!entity.is_untargetable());
+        assert_eq!(entity.buffs, vec![EntityBuff::Shield { duration: 3, amount: 6 }]);
+        assert_eq!(entity.health, health);
+    }
+
+    #[test]
+    fn buffs_of_a_kind_merge() {
+        let mut test_game = Game::new_client();
+        let bear = test_game.entities.create(entities::Controller::Initializer, 400, 100, units::test_card(units::UnitType::Bear, None));
+        let entity = test_game.entities.get_by_id_mut(bear).unwrap();
+        let health = entity.health;
+        for _ in 0..50 {
+            entity.apply_buff(EntityBuff::Shield { duration: u16::MAX, amount: 20 }, ruleset::CURRENT_RULESET_VERSION);
+            entity.apply_buff(EntityBuff::Slow { duration: 5, percent: 30 }, ruleset::CURRENT_RULESET_VERSION);
+        }
+        entity.apply_buff(EntityBuff::Slow { duration: 2, percent: 50 }, ruleset::CURRENT_RULESET_VERSION);
+        entity.apply_buff(EntityBuff::MaxHealth { duration: 5, amount: 30 }, ruleset::CURRENT_RULESET_VERSION);
+        entity.apply_buff(EntityBuff::MaxHealth { duration: 5, amount: 50 }, ruleset::CURRENT_RULESET_VERSION);
+        assert_eq!(entity.buffs, vec![
+            EntityBuff::Shield { duration: u16::MAX, amount: 20 },
+            EntityBuff::Slow { duration: 5, percent: 50 },
+            EntityBuff::MaxHealth { duration: 5, amount: 50 },
+        ]);
+        assert_eq!(entity.health, health + 50);
+
+        // before merging, stacks are capped
+        let entity = test_game.entities.get_by_id_mut(bear).unwrap();
+        for _ in 0..50 {
+            entity.apply_buff(EntityBuff::Shield { duration: u16::MAX, amount: 20 }, ruleset::BUFF_MERGING_VERSION - 1);
+        }
+        assert_eq!(entity.buffs.len(), entities::MAX_BUFFS);
+    }
+
+    #[test]
+    fn worst_case_game_fits_its_account() {
+        let card = units::test_card(units::UnitType::Hawk, Some(SpecialTrait::Guardian));
+        let buffs = [
+            EntityBuff::Stun { duration: 1 },
+            EntityBuff::Slow { duration: 1, percent: 1 },
+            EntityBuff::Poison { duration: 1, damage_per_tick: 1 },
+            EntityBuff::Shield { duration: 1, amount: 1 },
+            EntityBuff::Untargetable { duration: 1 },
+            EntityBuff::AttackSpeed { duration: 1, percent: 1 },
+            EntityBuff::MaxHealth { duration: 1, amount: 1 },
+            EntityBuff::Lifesteal { duration: 1, percent: 1 },
+        ];
+        for ruleset_version in [ruleset::BUFF_MERGING_VERSION - 1, ruleset::CURRENT_RULESET_VERSION] {
+            let mut test_game = Game::new_client();
+            test_game.ruleset_version = ruleset_version;
+            test_game.piece_limit = 5;
+            test_game.hand_size = 8;
+            for commitment in [
+                &mut test_game.i_commitment_1, &mut test_game.i_commitment_2, &mut test_game.o_commitment_1, &mut test_game.o_commitment_2,
+                &mut test_game.reveal_1, &mut test_game.reveal_2, &mut test_game.i_hand_root, &mut test_game.o_hand_root,
+                &mut test_game.i_deck_hash, &mut test_game.o_deck_hash, &mut test_game.i_adjustments_commitment, &mut test_game.o_adjustments_commitment,
+                &mut test_game.i_round_commitment, &mut test_game.o_round_commitment, &mut test_game.round_randomness,
+            ] {
+                *commitment = Some([255; 32]);
+            }
+            test_game.piece_timer = Some(i64::MAX);
+            test_game.i_inactivity_timer = Some(i64::MAX);
+            test_game.o_inactivity_timer = Some(i64::MAX);
+            test_game.draft_pool = vec![card.clone(); test_game.hand_size as usize * 2 + 4];
+            test_game.draft_picks = (0..test_game.hand_size * 2).collect();
+            test_game.unit_definitions = registry::default_unit_definitions();
+            for economy in [&mut test_game.i_economy, &mut test_game.o_economy] {
+                economy.shop = vec![Some(card.clone()); rounds::SHOP_SIZE as usize];
+                economy.board = vec![BoardUnit { card: card.clone(), grid_x: 0, grid_y: 0 }; 5];
+                economy.bench = vec![card.clone(); BENCH_SIZE as usize];
+            }
+            // a full board and bench for both players, every unit with a projectile in flight and buffed over and over
+            for player in [entities::Controller::Initializer, entities::Controller::Opponent] {
+                for _ in 0..(test_game.piece_limit + BENCH_SIZE) {
+                    let id = test_game.entities.create(player, 400, 400, card.clone());
+                    test_game.entities.get_by_id_mut(id).unwrap().bench_slot = Some(0);
+                    test_game.projectiles.add(Projectile {
+                        owner: player,
+                        source: id,
+                        position: utils::Location { x: 0, y: 0 },
+                        target: ProjectileTarget::Homing { target_id: id },
+                        speed: 1,
+                        damage: 1,
+                        crit: true,
+                    });
+                }
+            }
+            for entity in test_game.entities.all.iter_mut() {
+                entity.target = Some(0);
+                for _ in 0..100 {
+                    for buff in buffs {
+                        entity.apply_buff(buff, ruleset_version);
+                    }
+                }
+                assert!(entity.buffs.len() <= entities::MAX_BUFFS);
+            }
+            assert!(test_game.try_to_vec().unwrap().len() + 8 <= GAME_ACCOUNT_SPACE);
+        }
+    }
+
+    #[test]
+    fn attack_speed_scales_attack_timing() {
+        let card = |rarity| units::Card {
+            stats: units::get_baseline_unit_stats(ruleset::CURRENT_RULESET_VERSION, units::UnitType::Bull, rarity).unwrap(),
+            rarity,
+            ..units::test_card(units::UnitType::Bull, None)
+        };
+        let mut test_game = Game::new_client();
+        let mythic = test_game.entities.create(entities::Controller::Initializer, 400, 100, card(units::Rarity::Mythic));
+        test_game.entities.create(entities::Controller::Opponent, 400, 700, card(units::Rarity::Common));
+        test_game.step();
+
+        let entity = test_game.entities.get_by_id_mut(mythic).unwrap();
+        assert_eq!(entity.attack_speed_multiplier, 110);
+        entity.apply_buff(EntityBuff::AttackSpeed { duration: 10, percent: 90 }, ruleset::CURRENT_RULESET_VERSION);
+        assert_eq!(entity.attack_speed_multiplier, 209);
+        let base_duration = entity.stats.as_ref().unwrap().attack_duration;
+        assert_eq!(entity.get_effective_stats().unwrap().attack_duration, (base_duration * 100 + 208) / 209);
+    }
+
+    #[test]
+    fn units_path_around_a_wall() {
+        let mut test_game = Game::new_client();
+        let wolf = test_game.entities.create(entities::Controller::Initializer, 350, 50, units::test_card(units::UnitType::Wolf, None));
+        // a wall of bears across row 3, leaving only the rightmost cell open
+        let mut stunned = Vec::new();
+        for x in 0..7 {
+            stunned.push(test_game.entities.create(entities::Controller::Initializer, x * 100 + 50, 350, units::test_card(units::UnitType::Bear, None)));
+        }
+        stunned.push(test_game.entities.create(entities::Controller::Opponent, 350, 750, units::test_card(units::UnitType::Bull, None)));
+        for id in stunned {
+            test_game.entities.get_by_id_mut(id).unwrap().apply_buff(EntityBuff::Stun { duration: u16::MAX }, ruleset::CURRENT_RULESET_VERSION);
+        }
+
+        for _ in 0..100 {
+            test_game.step();
+            let entity = test_game.entities.get_by_id(wolf).unwrap();
+            let cell = pathfinding::cell_of(&entity.position);
+            assert!(cell / pathfinding::GRID_SIZE != 3 || cell % pathfinding::GRID_SIZE == 7, "walked into the wall at {:?}", entity.position);
+            if matches!(entity.state, EntityState::Attack{..}) {
+                return;
+            }
+        }
+        panic!("wolf never reached its target");
+    }
+
+    #[test]
+    fn assassins_target_the_backline() {
+        for (ruleset_version, expect_backline) in [(ruleset::PATHFINDING_VERSION, false), (ruleset::TARGETING_VERSION, true)] {
+            let mut test_game = Game::new_client();
+            test_game.ruleset_version = ruleset_version;
+            let assassin = test_game.entities.create(entities::Controller::Initializer, 400, 100, units::test_card(units::UnitType::Wolf, Some(SpecialTrait::Assassin)));
+            let front = test_game.entities.create(entities::Controller::Opponent, 450, 700, units::test_card(units::UnitType::Bear, None));
+            let back = test_game.entities.create(entities::Controller::Opponent, 50, 760, units::test_card(units::UnitType::Bear, None));
+            for id in [front, back] {
+                test_game.entities.get_by_id_mut(id).unwrap().apply_buff(EntityBuff::Stun { duration: u16::MAX }, ruleset::CURRENT_RULESET_VERSION);
+            }
+            // hop, land, then pick a target
+            for _ in 0..5 {
+                test_game.step();
+            }
+            let target = test_game.entities.get_by_id(assassin).unwrap().target;
+            assert_eq!(target, Some(if expect_backline { back } else { front }));
+        }
+    }
+
+    #[test]
+    fn traits_react_to_attacks_and_kills() {
+        let mut test_game = Game::new_client();
+        test_game.reveal_2 = Some([255; 32]);
+        // two duels far enough apart not to interfere
+        let berserker = test_game.entities.create(entities::Controller::Initializer, 100, 100, units::test_card(units::UnitType::Wolf, Some(SpecialTrait::Berserker)));
+        let victim = test_game.entities.create(entities::Controller::Opponent, 100, 200, units::test_card(units::UnitType::Bear, None));
+        let wolf = test_game.entities.create(entities::Controller::Initializer, 700, 100, units::test_card(units::UnitType::Wolf, None));
+        let thorns = test_game.entities.create(entities::Controller::Opponent, 700, 200, units::test_card(units::UnitType::Bear, Some(SpecialTrait::Thorns)));
+        test_game.entities.get_by_id_mut(victim).unwrap().health = 1;
+        for id in [victim, thorns] {
+            test_game.entities.get_by_id_mut(id).unwrap().apply_buff(EntityBuff::Stun { duration: u16::MAX }, ruleset::CURRENT_RULESET_VERSION);
+        }
+        let wolf_health = test_game.entities.get_by_id(wolf).unwrap().health;
+
+        for _ in 0..50 {
+            test_game.step();
+            if test_game.entities.get_by_id(victim).unwrap().state == EntityState::Dead {
+                break;
+            }
+        }
+        assert_eq!(test_game.entities.get_by_id(victim).unwrap().state, EntityState::Dead);
+        let berserker = test_game.entities.get_by_id(berserker).unwrap();
+        assert!(berserker.buffs.iter().any(|buff| matches!(buff, EntityBuff::AttackSpeed{..})));
+        assert!(berserker.attack_speed_multiplier > berserker.base_attack_speed_multiplier);
+        // the wolf attacked the thorns bear at the same time and took some back
+        assert!(test_game.entities.get_by_id(wolf).unwrap().health < wolf_health);
+    }
+
+    #[test]
+    fn deaths_are_handled_in_the_same_tick() {
+        let mut test_game = Game::new_client();
+        test_game.reveal_2 = Some([255; 32]);
+        let wolf = test_game.entities.create(entities::Controller::Initializer, 400, 100, units::test_card(units::UnitType::Wolf, None));
+        let guardian = test_game.entities.create(entities::Controller::Opponent, 400, 200, units::test_card(units::UnitType::Bear, Some(SpecialTrait::Guardian)));
+        let ally = test_game.entities.create(entities::Controller::Opponent, 500, 200, units::test_card(units::UnitType::Bear, None));
+        for id in [guardian, ally] {
+            test_game.entities.get_by_id_mut(id).unwrap().apply_buff(EntityBuff::Stun { duration: u16::MAX }, ruleset::CURRENT_RULESET_VERSION);
+        }
+        // step past the battle start shield before making the guardian fragile
+        test_game.step();
+        test_game.entities.get_by_id_mut(ally).unwrap().buffs.retain(|buff| !matches!(buff, EntityBuff::Shield{..}));
+        test_game.entities.get_by_id_mut(guardian).unwrap().health = 1;
+
+        for _ in 0..50 {
+            test_game.step();
+            if test_game.entities.get_by_id(guardian).unwrap().state == EntityState::Dead {
+                break;
+            }
+        }
+        assert_eq!(test_game.entities.get_by_id(guardian).unwrap().state, EntityState::Dead);
+        // the guardian's death shielded its ally and the killing blow paid out mana, all in the tick it died
+        assert!(test_game.entities.get_by_id(ally).unwrap().get_shield() > 0);
+        assert_eq!(test_game.entities.get_by_id(wolf).unwrap().mana, abilities::MANA_PER_ATTACK + abilities::MANA_PER_KILL);
+    }
+
+    #[test]
+    fn simultaneous_actions_resolve_the_same_in_any_order() {
+        let resolve = |reversed: bool| {
+            let mut test_game = Game::new_client();
+            test_game.ruleset_version = ruleset::CURRENT_RULESET_VERSION;
+            let bull = test_game.entities.create(entities::Controller::Initializer, 400, 100, units::test_card(units::UnitType::Bull, None));
+            let wolf = test_game.entities.create(entities::Controller::Opponent, 300, 300, units::test_card(units::UnitType::Wolf, None));
+            let hawk = test_game.entities.create(entities::Controller::Opponent, 500, 300, units::test_card(units::UnitType::Hawk, None));
+            test_game.entities.get_by_id_mut(bull).unwrap().health = 30;
+
+            // neither hit kills the bull alone, the heal can't save it, and the stun beats the state change
+            let mut queued = vec![
+                (bull, Action::Heal{amount: 50}),
+                (bull, Action::Damage{amount: 25, source: Some(wolf), crit: false, damage_type: units::DamageType::True}),
+                (bull, Action::Damage{amount: 20, source: Some(hawk), crit: true, damage_type: units::DamageType::True}),
+                (wolf, Action::EntityStateChange{state: EntityState::Attack{progress: 0, attack_on: 100, target_id: bull}}),
+                (wolf, Action::ApplyBuff{buff: EntityBuff::Stun{duration: 10}}),
+            ];
+            if reversed {
+                queued.reverse();
+            }
+            let mut actions = Actions::new();
+            for (id, action) in queued {
+                actions.add(id, action);
+            }
+            let mut events = Events::default();
+            test_game.apply_actions(&actions, &mut events);
+            let mut raised = Vec::new();
+            while let Some(event) = events.pop() {
+                raised.push(event);
+            }
+            (test_game, raised, bull, wolf, hawk)
+        };
+
+        let (test_game, raised, bull, wolf, hawk) = resolve(false);
+        assert_eq!(test_game.entities.get_by_id(bull).unwrap().state, EntityState::Dead);
+        assert_eq!(test_game.entities.get_by_id(wolf).unwrap().state, EntityState::Idle);
+        // the 30 health lost is shared 25:20, and the bigger hit gets the kill
+        assert!(raised.contains(&GameEvent::DamageDealt{source_id: wolf, target_id: bull, amount: 16}));
+        assert!(raised.contains(&GameEvent::DamageDealt{source_id: hawk, target_id: bull, amount: 13}));
+        assert!(raised.contains(&GameEvent::Crit{source_id: hawk, target_id: bull, amount: 20}));
+        assert!(raised.contains(&GameEvent::Death{entity_id: bull, killer_id: Some(wolf)}));
+        assert!(raised.contains(&GameEvent::Kill{entity_id: wolf, victim_id: bull}));
+        assert!(!raised.contains(&GameEvent::Kill{entity_id: hawk, victim_id: bull}));
+
+        let (reversed_game, reversed_raised, ..) = resolve(true);
+        assert_eq!(raised.len(), reversed_raised.len());
+        assert!(raised.iter().all(|event| reversed_raised.contains(event)));
+        for (a, b) in test_game.entities.all.iter().zip(reversed_game.entities.all.iter()) {
+            assert_eq!((a.health, a.state, a.mana, a.get_shield()), (b.health, b.state, b.mana, b.get_shield()));
+        }
+    }
+
+    #[test]
+    fn mirrored_battles_do_not_favour_creation_order() {
+        let battle = |opponent_first: bool| {
+            let mut test_game = Game::new_client();
+            test_game.ruleset_version = ruleset::CURRENT_RULESET_VERSION;
+            test_game.reveal_2 = Some([7; 32]);
+            let mut sides = vec![(entities::Controller::Initializer, 150), (entities::Controller::Opponent, 650)];
+            if opponent_first {
+                sides.reverse();
+            }
+            for (owner, y) in sides {
+                let mut bear = units::test_card(units::UnitType::Bear, None);
+                bear.stats.crit_chance = 0;
+                test_game.entities.create(owner, 400, y, bear);
+            }
+            while test_game.get_battle_result() == WinCondition::InProgress {
+                test_game.step();
+            }
+            (test_game.get_battle_result(), test_game.tick)
+        };
+        let (result, ticks) = battle(false);
+        // identical bears trade the same blows and go down together
+        assert_eq!(result, WinCondition::Tie);
+        assert_eq!(battle(true), (result, ticks));
+    }
+
+    #[test]
+    fn dead_units_keep_their_owner() {
+        let mut test_game = Game::new_client();
+        test_game.reveal_2 = Some([255; 32]);
+        test_game.entities.create(entities::Controller::Initializer, 400, 100, units::test_card(units::UnitType::Bear, None));
+        let wolf = test_game.entities.create(entities::Controller::Opponent, 400, 200, units::test_card(units::UnitType::Wolf, None));
+        test_game.entities.get_by_id_mut(wolf).unwrap().health = 1;
+        while test_game.get_battle_result() == WinCondition::InProgress {
+            test_game.step();
+        }
+
+        let dead = test_game.entities.get_by_id(wolf).unwrap();
+        assert_eq!(dead.state, EntityState::Dead);
+        assert_eq!(dead.owner, entities::Controller::Opponent);
+        assert_eq!(test_game.entities.count_for_controller(entities::Controller::Opponent), 0);
+        assert_eq!(test_game.entities.count_lost(entities::Controller::Opponent), 1);
+        assert_eq!(test_game.entities.count_lost(entities::Controller::Initializer), 0);
+        // dead units can't be found, targeted or shielded
+        assert!(test_game.entities.find_closest_entity(&dead.position, entities::Controller::Opponent).is_none());
+        assert!(test_game.entities.find_all_entities(&dead.position, u16::MAX, entities::Controller::Opponent).is_empty());
+        test_game.update_win_condition();
+        assert_eq!(test_game.win_condition, WinCondition::Initializer);
+    }
+
+    #[test]
+    fn healing_respects_max_health() {
+        let mut test_game = Game::new_client();
+        test_game.reveal_2 = Some([255; 32]);
+        let vampire = test_game.entities.create(entities::Controller::Initializer, 400, 100, units::test_card(units::UnitType::Wolf, Some(SpecialTrait::Vampiric)));
+        let bear = test_game.entities.create(entities::Controller::Opponent, 400, 200, units::test_card(units::UnitType::Bear, None));
+        test_game.entities.get_by_id_mut(bear).unwrap().apply_buff(EntityBuff::Stun { duration: u16::MAX }, ruleset::CURRENT_RULESET_VERSION);
+
+        // max health buffs raise health with them and take it back when they run out
+        let entity = test_game.entities.get_by_id_mut(bear).unwrap();
+        let max_health = entity.max_health;
+        entity.apply_buff(EntityBuff::MaxHealth { duration: 1, amount: 50 }, ruleset::CURRENT_RULESET_VERSION);
+        assert_eq!((entity.health, entity.max_health), (max_health + 50, max_health + 50));
+        entity.tick_buffs();
+        assert_eq!((entity.health, entity.max_health), (max_health, max_health));
+        // healing past max health turns partly into a shield
+        entity.heal(40, ruleset::HEALING_VERSION - 1);
+        assert_eq!(entity.get_shield(), 0);
+        entity.heal(40, ruleset::CURRENT_RULESET_VERSION);
+        assert_eq!(entity.get_shield(), 20);
+
+        let entity = test_game.entities.get_by_id_mut(vampire).unwrap();
+        entity.health = entity.max_health / 2;
+        let health = entity.health;
+        for _ in 0..20 {
+            test_game.step();
+        }
+        // vampiric lifesteal healed some of the damage dealt back
+        let entity = test_game.entities.get_by_id(vampire).unwrap();
+        assert_eq!(entity.lifesteal, 25);
+        assert!(entity.health > health);
+
+        // a battle that can't finish is decided on health after the time limit
+        test_game.tick = BATTLE_TICK_LIMIT;
+        test_game.entities.get_by_id_mut(bear).unwrap().buffs.clear();
+        test_game.entities.get_by_id_mut(bear).unwrap().health = 1;
+        assert_eq!(test_game.get_battle_result(), WinCondition::Initializer);
+        test_game.ruleset_version = ruleset::EVENTS_VERSION;
+        assert_eq!(test_game.get_battle_result(), WinCondition::InProgress);
+    }
+
+    #[test]
+    fn overheal_shield_stays_bounded() {
+        let mut test_game = Game::new_client();
+        let bear = test_game.entities.create(entities::Controller::Initializer, 400, 100, units::test_card(units::UnitType::Bear, None));
+        let entity = test_game.entities.get_by_id_mut(bear).unwrap();
+        let cap = entity.max_health / 4;
+        for _ in 0..200 {
+            entity.heal(40, ruleset::CURRENT_RULESET_VERSION);
+        }
+        assert_eq!(entity.get_shield(), cap);
+        assert_eq!(entity.buffs, vec![EntityBuff::Shield { duration: 30, amount: cap }]);
+
+        // a bigger shield isn't cut down, and overheal doesn't add to it
+        entity.apply_buff(EntityBuff::Shield { duration: 5, amount: cap * 2 }, ruleset::CURRENT_RULESET_VERSION);
+        entity.heal(40, ruleset::CURRENT_RULESET_VERSION);
+        assert_eq!(entity.buffs, vec![EntityBuff::Shield { duration: 30, amount: cap * 2 }]);
+    }
+
+    #[test]
+    fn armor_mitigates_crits_after_doubling() {
+        let mut test_game = Game::new_client();
+        // every roll crits. Rolls only come straight from reveal_2 before PHASES_VERSION.
+        test_game.ruleset_version = ruleset::MITIGATION_VERSION;
+        test_game.reveal_2 = Some([0; 32]);
+        let wolf = test_game.entities.create(entities::Controller::Initializer, 400, 100, units::test_card(units::UnitType::Wolf, None));
+        let bear = test_game.entities.create(entities::Controller::Opponent, 400, 200, units::test_card(units::UnitType::Bear, None));
+        test_game.entities.get_by_id_mut(bear).unwrap().apply_buff(EntityBuff::Stun { duration: u16::MAX }, ruleset::CURRENT_RULESET_VERSION);
+
+        let entity = test_game.entities.get_by_id(bear).unwrap();
+        let stats = entity.stats.clone().unwrap();
+        assert!(stats.armor > stats.magic_resist);
+        assert!(entity.mitigate(100, units::DamageType::Physical) < entity.mitigate(100, units::DamageType::Magic));
+        assert_eq!(entity.mitigate(100, units::DamageType::True), 100);
+        assert_eq!(entity.mitigate(1, units::DamageType::Physical), 1);
+
+        let health = entity.health;
+        let attack_damage = test_game.entities.get_by_id(wolf).unwrap().stats.as_ref().unwrap().attack_damage;
+        while test_game.entities.get_by_id(bear).unwrap().health == health {
+            test_game.step();
+        }
+        let expected = (attack_damage as u32 * 2 * 100 / (100 + stats.armor as u32)) as u16;
+        assert_eq!(test_game.entities.get_by_id(bear).unwrap().health, health - expected);
+    }
+
+    #[test]
+    fn living_units_keep_their_distance() {
+        for seed in 0..50 {
+            let battle = test_vectors::run_seeded_battle(seed, ruleset::COLLISION_VERSION);
+            let living: Vec<&entities::Entity> = battle.entities.all.iter()
+                .filter(|entity| !matches!(entity.state, EntityState::Dead | EntityState::Airborne{..}))
+                .collect();
+            for (n, a) in living.iter().enumerate() {
+                for b in &living[n + 1..] {
+                    assert!(a.position.distance(&b.position) >= collision::MIN_SEPARATION - 1, "seed {}: {} and {} overlap", seed, a.id, b.id);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn draft_alternates_picks() {
+        let mut test_game = Game::new_client();
+        test_game.hand_mode = HandMode::Draft;
+        test_game.hand_size = 2;
+        test_game.reveal_1 = Some([4; 32]);
+        test_game.start_draft(0);
+        assert_eq!(test_game.state, 6);
+        assert_eq!(test_game.draft_pool.len(), 8);
+
+        let first = test_game.get_draft_turn();
+        let second = if first == entities::Controller::Initializer { entities::Controller::Opponent } else { entities::Controller::Initializer };
+        assert!(test_game.draft_pick(second, 0, 0).is_err());
+        test_game.draft_pick(first, 3, 0).unwrap();
+        // can't take a card that's already gone
+        assert!(test_game.draft_pick(second, 3, 0).is_err());
+        test_game.draft_pick(second, 0, 0).unwrap();
+        test_game.draft_pick(first, 5, 0).unwrap();
+        test_game.draft_pick(second, 1, 0).unwrap();
+
+        assert_eq!(test_game.state, 2);
+        let pool = test_game.draft_pool.clone();
+        assert_eq!(test_game.get_draft_hand(first), vec![pool[3].clone(), pool[5].clone()]);
+        assert_eq!(test_game.get_draft_hand(second), vec![pool[0].clone(), pool[1].clone()]);
+    }
+
+    #[test]
+    fn draft_games_stay_single_round() {
+        let mut test_game = Game::new_client();
+        test_game.configure_hand_mode(HandMode::Draft).unwrap();
+        assert!(test_game.configure_multi_round().is_err());
+        assert!(!test_game.multi_round);
+
+        let mut test_game = Game::new_client();
+        test_game.configure_multi_round().unwrap();
+        assert!(test_game.configure_hand_mode(HandMode::Draft).is_err());
+        assert_eq!(test_game.hand_mode, HandMode::Random);
+        test_game.configure_hand_mode(HandMode::Deck).unwrap();
+    }
+
+    #[test]
+    fn reveal_collection_pieces_checks_owner() {
+        let mut test_game = Game::new_client();
+        test_game.hand_mode = HandMode::Collection;
+        test_game.hand_size = 8;
+        test_game.piece_limit = 5;
+        test_game.initializer = Pubkey::new_unique();
+
+        let asset_key = Pubkey::new_unique();
+        let leaves = vec![merkle::hash_asset_leaf(0, &asset_key, &[0; 32])];
+        test_game.commit_hand(entities::Controller::Initializer, merkle::compute_root(&leaves)).unwrap();
+        test_game.place_piece_hidden(entities::Controller::Initializer, 1, 1, 0).unwrap();
+
+        let reveals = vec![AssetReveal { hand_position: 0, asset: asset_key, salt: [0; 32], proof: Vec::new() }];
+        let mut asset = CardAsset {
+            id: 0,
+            owner: Pubkey::new_unique(),
+            card: units::test_card(units::UnitType::Wolf, None),
+        };
+        assert!(test_game.reveal_collection_pieces(entities::Controller::Initializer, &reveals, &vec![asset.clone()]).is_err());
+
+        asset.owner = test_game.initializer;
+        assert!(test_game.reveal_collection_pieces(entities::Controller::Initializer, &reveals, &vec![asset]).is_ok());
+        assert_eq!(test_game.entities.all[0].unit_type, units::UnitType::Wolf);
+    }
 }
\ No newline at end of file
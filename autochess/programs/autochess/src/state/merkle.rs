@@ -0,0 +1,161 @@
+use anchor_lang::{solana_program::hash::hashv, prelude::*};
+use super::units::Card;
+
+/// Prefixes keep a leaf from ever being passed off as an inner node
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+/// A single card revealed out of a committed hand, along with the path up to the hand root
+#[derive(Debug, AnchorDeserialize, AnchorSerialize, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CardReveal {
+    pub hand_position: u8,
+    pub card: Card,
+    /// Per card salt so the unrevealed leaves in a proof can't be brute forced
+    pub salt: [u8; 32],
+    pub proof: Vec<[u8; 32]>,
+}
+
+impl CardReveal {
+    pub fn leaf(&self) -> [u8; 32] {
+        hash_card_leaf(self.hand_position, &self.card, &self.salt)
+    }
+
+    /// Check that this card sits at hand_position in the hand committed to by root
+    pub fn verify(&self, root: &[u8; 32]) -> bool {
+        verify_proof(root, &self.leaf(), self.hand_position as usize, &self.proof)
+    }
+}
+
+/// A card asset revealed out of a committed collection hand. The card itself is read from the asset account.
+#[derive(Debug, AnchorDeserialize, AnchorSerialize, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AssetReveal {
+    pub hand_position: u8,
+    pub asset: Pubkey,
+    pub salt: [u8; 32],
+    pub proof: Vec<[u8; 32]>,
+}
+
+impl AssetReveal {
+    pub fn leaf(&self) -> [u8; 32] {
+        hash_asset_leaf(self.hand_position, &self.asset, &self.salt)
+    }
+
+    /// Check that this asset sits at hand_position in the hand committed to by root
+    pub fn verify(&self, root: &[u8; 32]) -> bool {
+        verify_proof(root, &self.leaf(), self.hand_position as usize, &self.proof)
+    }
+}
+
+fn hash_leaf(hand_position: u8, data: &[u8], salt: &[u8; 32]) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, &[hand_position], data, salt]).to_bytes()
+}
+
+pub fn hash_card_leaf(hand_position: u8, card: &Card, salt: &[u8; 32]) -> [u8; 32] {
+    hash_leaf(hand_position, &card.try_to_vec().unwrap(), salt)
+}
+
+pub fn hash_asset_leaf(hand_position: u8, asset: &Pubkey, salt: &[u8; 32]) -> [u8; 32] {
+    hash_leaf(hand_position, asset.as_ref(), salt)
+}
+
+fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    hashv(&[NODE_PREFIX, left, right]).to_bytes()
+}
+
+/// Hash one level of the tree. An odd node at the end is paired with itself.
+fn next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level.chunks(2)
+        .map(|pair| hash_node(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+        .collect()
+}
+
+/// Compute the root of a hand given its leaves in hand order. Client side.
+pub fn compute_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+/// Build the sibling path for the leaf at index. Client side.
+pub fn build_proof(leaves: &[[u8; 32]], index: usize) -> Vec<[u8; 32]> {
+    let mut proof = Vec::new();
+    let mut level = leaves.to_vec();
+    let mut index = index;
+    while level.len() > 1 {
+        let sibling = index ^ 1;
+        proof.push(*level.get(sibling).unwrap_or(&level[index]));
+        level = next_level(&level);
+        index /= 2;
+    }
+    proof
+}
+
+/// Walk a sibling path up from a leaf and compare against the stored root
+pub fn verify_proof(root: &[u8; 32], leaf: &[u8; 32], index: usize, proof: &[[u8; 32]]) -> bool {
+    let mut node = *leaf;
+    let mut index = index;
+    for sibling in proof {
+        node = if index % 2 == 0 {
+            hash_node(&node, sibling)
+        } else {
+            hash_node(sibling, &node)
+        };
+        index /= 2;
+    }
+    // index must have been fully consumed, otherwise a shorter proof could point at an inner node
+    index == 0 && node == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::units::{self, UnitType};
+
+    fn test_hand(n: u8) -> (Vec<Card>, Vec<[u8; 32]>) {
+        let types = [UnitType::Wolf, UnitType::Bear, UnitType::Bull];
+        let cards: Vec<Card> = (0..n).map(|i| units::test_card(types[i as usize % 3], None)).collect();
+        let leaves = cards.iter().enumerate()
+            .map(|(i, card)| hash_card_leaf(i as u8, card, &[i as u8; 32]))
+            .collect();
+        (cards, leaves)
+    }
+
+    #[test]
+    fn proofs_verify_for_every_position() {
+        for n in [1, 5, 8] {
+            let (cards, leaves) = test_hand(n);
+            let root = compute_root(&leaves);
+            for (i, card) in cards.iter().enumerate() {
+                let reveal = CardReveal {
+                    hand_position: i as u8,
+                    card: card.clone(),
+                    salt: [i as u8; 32],
+                    proof: build_proof(&leaves, i),
+                };
+                assert!(reveal.verify(&root), "hand size {} position {}", n, i);
+            }
+        }
+    }
+
+    #[test]
+    fn proofs_reject_swapped_cards() {
+        let (cards, leaves) = test_hand(8);
+        let root = compute_root(&leaves);
+        let reveal = CardReveal {
+            hand_position: 1,
+            card: cards[0].clone(),
+            salt: [1; 32],
+            proof: build_proof(&leaves, 1),
+        };
+        assert!(!reveal.verify(&root));
+        let moved = CardReveal {
+            hand_position: 2,
+            card: cards[1].clone(),
+            salt: [1; 32],
+            proof: build_proof(&leaves, 1),
+        };
+        assert!(!moved.verify(&root));
+    }
+}
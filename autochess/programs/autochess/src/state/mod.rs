@@ -3,4 +3,20 @@ pub mod entities;
 pub mod units;
 pub mod utils;
 pub mod projectiles;
-pub mod actions;
\ No newline at end of file
+pub mod actions;
+pub mod merkle;
+pub mod collection;
+pub mod deck;
+pub mod rounds;
+pub mod synergies;
+pub mod registry;
+pub mod ruleset;
+pub mod abilities;
+pub mod fixed;
+pub mod collision;
+pub mod pathfinding;
+pub mod targeting;
+pub mod traits;
+pub mod events;
+#[cfg(any(test, feature = "test-vectors"))]
+pub mod test_vectors;
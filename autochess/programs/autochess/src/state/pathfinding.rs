@@ -0,0 +1,185 @@
+//! A* over the 8 x 8 board grid, so units walk around other units instead of through them.
+//! The grid is tiny, so the open set is a plain list scanned for the best cell. Blockers are worked out once per tick
+//! and shared by every moving unit. From PATH_BUDGET_VERSION a search gives up after MAX_EXPANSIONS cells, so a tick
+//! on a full 16 unit board costs at most 16 bounded searches. Compute use on BPF hasn't been measured, so crank a
+//! battle in small batches of steps.
+
+use super::{utils::Location, entities::{Entities, EntityState}};
+
+pub const GRID_SIZE: usize = 8;
+/// Width of one grid cell in board units
+pub const CELL_SIZE: u16 = 100;
+const CELLS: usize = GRID_SIZE * GRID_SIZE;
+/// Cells a search may expand from PATH_BUDGET_VERSION. Enough to find the gap in a wall across the whole board.
+pub const MAX_EXPANSIONS: usize = 40;
+const STRAIGHT_COST: u16 = 10;
+const DIAGONAL_COST: u16 = 14;
+/// Neighbour offsets in the order they're expanded. Fixed so ties always resolve the same way.
+const NEIGHBOURS: [(i8, i8); 8] = [(0, -1), (1, 0), (0, 1), (-1, 0), (1, -1), (1, 1), (-1, 1), (-1, -1)];
+
+/// Cells that are blocked for pathing, indexed by y * GRID_SIZE + x
+pub type Blockers = [bool; CELLS];
+
+pub fn cell_of(position: &Location) -> usize {
+    let x = (position.x / CELL_SIZE).min(GRID_SIZE as u16 - 1) as usize;
+    let y = (position.y / CELL_SIZE).min(GRID_SIZE as u16 - 1) as usize;
+    y * GRID_SIZE + x
+}
+
+pub fn cell_center(cell: usize) -> Location {
+    Location {
+        x: (cell % GRID_SIZE) as u16 * CELL_SIZE + CELL_SIZE / 2,
+        y: (cell / GRID_SIZE) as u16 * CELL_SIZE + CELL_SIZE / 2,
+    }
+}
+
+/// Cells taken up by living units on the board. Airborne units don't block.
+/// The mover and its target don't need leaving out, since find_path never treats the start and goal as blocked.
+pub fn get_blockers(entities: &Entities) -> Blockers {
+    let mut blockers = [false; CELLS];
+    for entity in &entities.all {
+        if entity.is_benched() || matches!(entity.state, EntityState::Dead | EntityState::Airborne{..}) {
+            continue;
+        }
+        blockers[cell_of(&entity.position)] = true;
+    }
+    blockers
+}
+
+/// Octile distance, the exact cost between two cells on an empty grid
+fn heuristic(from: usize, to: usize) -> u16 {
+    let dx = ((from % GRID_SIZE) as i16 - (to % GRID_SIZE) as i16).unsigned_abs();
+    let dy = ((from / GRID_SIZE) as i16 - (to / GRID_SIZE) as i16).unsigned_abs();
+    let (low, high) = if dx < dy { (dx, dy) } else { (dy, dx) };
+    DIAGONAL_COST * low + STRAIGHT_COST * (high - low)
+}
+
+/// Shortest path of cells from start to goal, both included. The start and goal cells are never treated as blocked.
+/// Diagonal steps may not cut the corner of a blocked cell. Ties are broken by lowest estimated total, then
+/// lowest remaining estimate, then lowest cell index, so the same board always gives the same path.
+/// Returns None if the goal can't be reached, or wasn't found within max_expansions expanded cells.
+pub fn find_path(start: usize, goal: usize, blockers: &Blockers, max_expansions: usize) -> Option<Vec<usize>> {
+    let blocked = |cell: usize| cell != start && cell != goal && blockers[cell];
+    let mut cost = [u16::MAX; CELLS];
+    let mut came_from = [usize::MAX; CELLS];
+    let mut open: Vec<usize> = vec![start];
+    let mut closed = [false; CELLS];
+    cost[start] = 0;
+
+    let mut expansions = 0;
+    loop {
+        let mut current: Option<(u16, u16, usize)> = None;
+        for &cell in &open {
+            let remaining = heuristic(cell, goal);
+            let key = (cost[cell] + remaining, remaining, cell);
+            if current.map_or(true, |best| key < best) {
+                current = Some(key);
+            }
+        }
+        let current = current?.2;
+        if current == goal {
+            break;
+        }
+        if expansions == max_expansions {
+            return None;
+        }
+        expansions += 1;
+        open.retain(|cell| *cell != current);
+        closed[current] = true;
+
+        let (x, y) = ((current % GRID_SIZE) as i8, (current / GRID_SIZE) as i8);
+        for &(dx, dy) in NEIGHBOURS.iter() {
+            let (nx, ny) = (x + dx, y + dy);
+            if nx < 0 || ny < 0 || nx >= GRID_SIZE as i8 || ny >= GRID_SIZE as i8 {
+                continue;
+            }
+            let neighbour = ny as usize * GRID_SIZE + nx as usize;
+            if closed[neighbour] || blocked(neighbour) {
+                continue;
+            }
+            let diagonal = dx != 0 && dy != 0;
+            if diagonal && (blocked(y as usize * GRID_SIZE + nx as usize) || blocked(ny as usize * GRID_SIZE + x as usize)) {
+                continue;
+            }
+            let step_cost = if diagonal { DIAGONAL_COST } else { STRAIGHT_COST };
+            let new_cost = cost[current] + step_cost;
+            if new_cost < cost[neighbour] {
+                if cost[neighbour] == u16::MAX {
+                    open.push(neighbour);
+                }
+                cost[neighbour] = new_cost;
+                came_from[neighbour] = current;
+            }
+        }
+    }
+
+    let mut path = vec![goal];
+    let mut cell = goal;
+    while cell != start {
+        cell = came_from[cell];
+        path.push(cell);
+    }
+    path.reverse();
+    Some(path)
+}
+
+/// Point to walk towards on the way from `from` to `to`. Once the target is in the next cell over, or no way around
+/// the blockers was found, this is the target itself; otherwise it's the center of the next cell on the path.
+pub fn next_waypoint(from: &Location, to: &Location, blockers: &Blockers, max_expansions: usize) -> Location {
+    match find_path(cell_of(from), cell_of(to), blockers, max_expansions) {
+        Some(path) if path.len() > 2 => cell_center(path[1]),
+        _ => *to,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(x: usize, y: usize) -> usize {
+        y * GRID_SIZE + x
+    }
+
+    #[test]
+    fn paths_around_a_wall() {
+        // wall across row 3 with a single gap at the right edge
+        let mut blockers = [false; CELLS];
+        for x in 0..7 {
+            blockers[cell(x, 3)] = true;
+        }
+        let path = find_path(cell(0, 0), cell(0, 6), &blockers, usize::MAX).unwrap();
+        assert_eq!(path.first(), Some(&cell(0, 0)));
+        assert_eq!(path.last(), Some(&cell(0, 6)));
+        assert!(path.contains(&cell(7, 3)));
+        assert!(path.iter().all(|c| !blockers[*c]));
+        // same board, same path
+        assert_eq!(find_path(cell(0, 0), cell(0, 6), &blockers, usize::MAX), Some(path));
+
+        // closing the gap leaves no way through
+        blockers[cell(7, 3)] = true;
+        assert_eq!(find_path(cell(0, 0), cell(0, 6), &blockers, usize::MAX), None);
+        assert_eq!(next_waypoint(&cell_center(cell(0, 0)), &cell_center(cell(0, 6)), &blockers, usize::MAX), cell_center(cell(0, 6)));
+    }
+
+    #[test]
+    fn searches_give_up_after_their_budget() {
+        let mut blockers = [false; CELLS];
+        for x in 0..7 {
+            blockers[cell(x, 3)] = true;
+        }
+        assert!(find_path(cell(0, 0), cell(0, 6), &blockers, usize::MAX).is_some());
+        assert_eq!(find_path(cell(0, 0), cell(0, 6), &blockers, 3), None);
+        // out of budget, walk straight at the target
+        assert_eq!(next_waypoint(&cell_center(cell(0, 0)), &cell_center(cell(0, 6)), &blockers, 3), cell_center(cell(0, 6)));
+    }
+
+    #[test]
+    fn open_board_walks_straight() {
+        let blockers = [false; CELLS];
+        let path = find_path(cell(2, 1), cell(2, 5), &blockers, MAX_EXPANSIONS).unwrap();
+        assert_eq!(path, vec![cell(2, 1), cell(2, 2), cell(2, 3), cell(2, 4), cell(2, 5)]);
+        // neighbouring cells go straight for the target
+        let to = Location { x: 260, y: 320 };
+        assert_eq!(next_waypoint(&Location { x: 250, y: 250 }, &to, &blockers, MAX_EXPANSIONS), to);
+    }
+}
@@ -1,71 +1,90 @@
-// use anchor_lang::{prelude::*};
-// use super::{utils::Location};
+use anchor_lang::{prelude::*};
+use super::{utils::Location, entities::{Entities, EntityState, Controller}, actions::{Action, Actions, Hit}, units::DamageType};
 
-// #[derive(Debug, Default, AnchorDeserialize, AnchorSerialize, Clone)]
-// pub struct Projectiles {
-//     pub all: Vec<Projectile>,
-// }
+/// Distance at which a projectile counts as arrived
+const HIT_RADIUS: u16 = 20;
 
-// impl Projectiles {
-//     pub fn get(&mut self) {
-//         for projectile in &mut self.all {
-//             match projectile {
-//                 Projectile::RangedAttack(p) => p.step(),
-//                 Projectile::Explosion(p) => p.step(),
-//                 Projectile::None => {},
-//             }
-//         }
-//     }
-// }
+/// Projectiles in flight. Advanced once per tick in Game::step, after entities have queued their actions.
+#[derive(Debug, Default, AnchorDeserialize, AnchorSerialize, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Projectiles {
+    pub all: Vec<Projectile>,
+}
 
-// // #[derive(Debug, Default, AnchorDeserialize, AnchorSerialize, Clone, Copy)]
-// // pub struct Projectile {
-// //     position: Location,
-// //     speed: u16,
-// //     target: Entity,
-// // }
+impl Projectiles {
+    pub fn add(&mut self, projectile: Projectile) {
+        self.all.push(projectile);
+    }
 
-// #[derive(Debug, AnchorDeserialize, AnchorSerialize, Clone)]
-// pub enum Projectile {
-//     None,
-//     RangedAttack(RangedAttack),
-//     Explosion(Explosion),
-// }
+    /// Move every projectile and queue damage for the ones that hit. Hit or lost projectiles are removed.
+    /// Returns the hits, in the order the projectiles were fired.
+    pub fn step(&mut self, entities: &Entities, actions: &mut Actions) -> Vec<Hit> {
+        let mut in_flight = Vec::new();
+        let mut hits = Vec::new();
+        for mut projectile in self.all.drain(..) {
+            if projectile.step(entities, actions, &mut hits) {
+                in_flight.push(projectile);
+            }
+        }
+        self.all = in_flight;
+        hits
+    }
+}
 
-// pub trait ProjectileTrait {
-//     fn step(&mut self);
-// }
+#[derive(Debug, PartialEq, AnchorDeserialize, AnchorSerialize, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum ProjectileTarget {
+    /// Follows an entity until it hits. Dropped if the entity dies first.
+    Homing{target_id: u16},
+    /// Flies to a point and hits the closest enemy of its owner within HIT_RADIUS, if any
+    Location{to: Location},
+}
 
-// #[derive(Debug, Default, AnchorDeserialize, AnchorSerialize, Clone)]
-// pub struct RangedAttack {
-//     pub position: Location,
-//     pub target_id: u16,
-// }
+#[derive(Debug, PartialEq, AnchorDeserialize, AnchorSerialize, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Projectile {
+    /// Controller of the entity that fired it
+    pub owner: Controller,
+    /// Id of the entity that fired it
+    pub source: u16,
+    pub position: Location,
+    pub target: ProjectileTarget,
+    /// Distance per tick
+    pub speed: u16,
+    /// Damage dealt on impact, with crit already applied
+    pub damage: u16,
+    pub crit: bool,
+}
 
-// impl ProjectileTrait for RangedAttack {
-//     fn step(&mut self) {
-//         self.position.x = self.position.x + 1 ;
-//     }
-// }
-// #[derive(Debug, Default, AnchorDeserialize, AnchorSerialize, Clone)]
-// pub struct Explosion {
-//     pub position: Location,
-//     pub target_id: u16,
-// }
-// impl ProjectileTrait for Explosion {
-//     fn step(&mut self) {
-//         self.position.x = self.position.x + 21 ;
-//     }
-// }
+impl Projectile {
+    /// Advance one tick. Returns false once the projectile is done.
+    fn step(&mut self, entities: &Entities, actions: &mut Actions, hits: &mut Vec<Hit>) -> bool {
+        let to = match self.target {
+            ProjectileTarget::Homing{target_id} => match entities.get_by_id(target_id) {
+                Some(target) if target.state != EntityState::Dead => target.position,
+                _ => return false,
+            },
+            ProjectileTarget::Location{to} => to,
+        };
+        let distance = self.position.distance(&to);
+        if distance <= self.speed.max(HIT_RADIUS) {
+            self.position = to;
+            self.impact(entities, actions, hits);
+            return false;
+        }
+        self.position = self.position.move_towards(&to, distance, self.speed);
+        true
+    }
 
-// #[derive(Debug, Default, AnchorDeserialize, AnchorSerialize, Clone)]
-// pub struct StaticTarget {
-//     pub position: Location,
-//     pub target: Location,
-// }
-
-// impl Default for Projectile {
-//     fn default() -> Projectile {
-//         Projectile::None
-//     }
-// }
\ No newline at end of file
+    fn impact(&self, entities: &Entities, actions: &mut Actions, hits: &mut Vec<Hit>) {
+        let target_id = match self.target {
+            ProjectileTarget::Homing{target_id} => target_id,
+            ProjectileTarget::Location{to} => {
+                let enemy = if self.owner == Controller::Initializer { Controller::Opponent } else { Controller::Initializer };
+                match entities.find_closest_entity_in_range(&to, HIT_RADIUS, enemy) {
+                    Some(result) => result.entity.id,
+                    None => return,
+                }
+            },
+        };
+        actions.add(target_id, Action::Damage{amount: self.damage, source: Some(self.source), crit: self.crit, damage_type: DamageType::Physical});
+        hits.push(Hit { attacker_id: self.source, target_id, damage: self.damage });
+    }
+}
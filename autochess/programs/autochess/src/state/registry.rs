@@ -0,0 +1,71 @@
+use anchor_lang::prelude::*;
+use super::{units::{self, UnitType, UnitStats, Rarity}, ruleset::CURRENT_RULESET_VERSION};
+
+pub const REGISTRY_UNIT_TYPES: [UnitType; 4] = [UnitType::Wolf, UnitType::Bull, UnitType::Bear, UnitType::Hawk];
+pub const REGISTRY_RARITIES: [Rarity; 6] = [Rarity::Common, Rarity::Uncommon, Rarity::Rare, Rarity::Epic, Rarity::Legendary, Rarity::Mythic];
+
+/// Unit stat lines the admin can patch without a program upgrade. PDA with seed "UnitRegistry".
+/// Games snapshot the definitions when they're created, so a patch never changes a game in progress.
+#[account]
+#[derive(Default)]
+pub struct UnitRegistry {
+    /// Only this key can update the definitions
+    pub authority: Pubkey,
+    /// Bumped on every update
+    pub version: u32,
+    pub units: Vec<UnitDefinition>,
+}
+
+/// Baseline (1 star) stats for a unit type at a rarity
+#[derive(Debug, PartialEq, AnchorDeserialize, AnchorSerialize, Clone, serde::Serialize, serde::Deserialize)]
+pub struct UnitDefinition {
+    pub unit_type: UnitType,
+    pub rarity: Rarity,
+    pub stats: UnitStats,
+}
+
+/// The built in stat lines, used to seed a new registry
+pub fn default_unit_definitions() -> Vec<UnitDefinition> {
+    let mut definitions = Vec::new();
+    for unit_type in REGISTRY_UNIT_TYPES {
+        for rarity in REGISTRY_RARITIES {
+            definitions.push(UnitDefinition {
+                unit_type,
+                rarity,
+                stats: units::get_baseline_unit_stats(CURRENT_RULESET_VERSION, unit_type, rarity).unwrap(),
+            });
+        }
+    }
+    definitions
+}
+
+/// Definitions must have exactly one entry for each unit type and rarity, and no hidden units
+pub fn validate_definitions(definitions: &Vec<UnitDefinition>) -> bool {
+    definitions.len() == REGISTRY_UNIT_TYPES.len() * REGISTRY_RARITIES.len()
+        && REGISTRY_UNIT_TYPES.iter().all(|unit_type| REGISTRY_RARITIES.iter().all(|rarity| {
+            definitions.iter().filter(|definition| definition.unit_type == *unit_type && definition.rarity == *rarity).count() == 1
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::ruleset::Ruleset;
+
+    #[test]
+    fn registry_overrides_builtin_stats() {
+        let mut definitions = default_unit_definitions();
+        assert!(validate_definitions(&definitions));
+        let builtin = Ruleset::current();
+        let mut registry_ruleset = Ruleset { unit_definitions: definitions.clone(), ..Ruleset::current() };
+        assert_eq!(registry_ruleset.get_unit_stats(UnitType::Bear, Rarity::Rare, 2), builtin.get_unit_stats(UnitType::Bear, Rarity::Rare, 2));
+
+        definitions[0].stats.attack_damage = 99;
+        registry_ruleset.unit_definitions = definitions.clone();
+        let patched = registry_ruleset.get_unit_stats(definitions[0].unit_type, definitions[0].rarity, 1).unwrap();
+        assert_eq!(patched.attack_damage, 99);
+
+        definitions.push(definitions[0].clone());
+        assert!(!validate_definitions(&definitions));
+    }
+}
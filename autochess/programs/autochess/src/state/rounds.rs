@@ -0,0 +1,144 @@
+use anchor_lang::{solana_program::hash::hashv, prelude::*};
+use super::{units::{self, Card, Rarity}, entities::Controller, game::BENCH_SIZE, ruleset::Ruleset};
+
+pub const STARTING_HP: u8 = 20;
+pub const STARTING_GOLD: u8 = 5;
+/// Gold given to each player at the start of every round after the first
+pub const GOLD_PER_ROUND: u8 = 5;
+/// Extra gold for winning a round
+pub const GOLD_PER_WIN: u8 = 1;
+pub const SHOP_SIZE: u8 = 5;
+pub const REROLL_COST: u8 = 1;
+/// Damage dealt to the loser of a round, on top of 1 per surviving enemy unit
+pub const BASE_ROUND_DAMAGE: u8 = 2;
+
+/// Per player state that persists across rounds in a multi round game
+#[derive(Debug, Default, AnchorDeserialize, AnchorSerialize, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PlayerEconomy {
+    pub hp: u8,
+    pub gold: u8,
+    pub shop: Vec<Option<Card>>,
+    /// Number of rerolls this round. Part of the shop's randomness so each reroll gives a new shop.
+    pub rerolls: u8,
+    /// Saved board layout. Each round's battle is started from this.
+    pub board: Vec<BoardUnit>,
+    /// Units kept off the board, up to BENCH_SIZE. They sit out battles but still merge.
+    pub bench: Vec<Card>,
+}
+
+impl PlayerEconomy {
+    pub fn new() -> Self {
+        PlayerEconomy {
+            hp: STARTING_HP,
+            gold: STARTING_GOLD,
+            shop: Vec::new(),
+            rerolls: 0,
+            board: Vec::new(),
+            bench: Vec::new(),
+        }
+    }
+
+    pub fn spend(&mut self, amount: u8) -> bool {
+        match self.gold.checked_sub(amount) {
+            Some(gold) => {
+                self.gold = gold;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Every unit the player owns, board first and then bench
+    fn all_cards(&self) -> Vec<Card> {
+        self.board.iter().map(|unit| unit.card.clone()).chain(self.bench.iter().cloned()).collect()
+    }
+
+    /// Whether buying this card would complete a merge with two units already on the board or bench
+    pub fn would_merge(&self, card: &Card) -> bool {
+        card.star_level < units::MAX_STAR_LEVEL && self.all_cards().iter().filter(|other| other.merges_with(card)).count() >= 2
+    }
+
+    /// Merge any three matching units on the board or bench into the place of the first one.
+    /// Board units come first, so a merge involving a board unit stays on the board.
+    /// Repeats so that a merge can chain into a 3 star.
+    pub fn merge_units(&mut self, ruleset: &Ruleset) {
+        loop {
+            let cards = self.all_cards();
+            let mut group: Option<(usize, usize, usize)> = None;
+            for i in 0..cards.len() {
+                let matching: Vec<usize> = (i + 1..cards.len())
+                    .filter(|j| cards[i].merges_with(&cards[*j]))
+                    .collect();
+                if matching.len() >= 2 && cards[i].star_level < units::MAX_STAR_LEVEL {
+                    group = Some((i, matching[0], matching[1]));
+                    break;
+                }
+            }
+            match group {
+                Some((i, j, k)) => {
+                    let merged = units::merge_cards(&cards[i], &cards[j], &cards[k], ruleset).unwrap();
+                    self.set_card(i, merged);
+                    // remove the later index first so the earlier one stays valid
+                    self.remove_card(k);
+                    self.remove_card(j);
+                },
+                None => break,
+            }
+        }
+    }
+
+    /// Replace a card by its index into all_cards
+    fn set_card(&mut self, index: usize, card: Card) {
+        if index < self.board.len() {
+            self.board[index].card = card;
+        } else {
+            self.bench[index - self.board.len()] = card;
+        }
+    }
+
+    /// Remove a card by its index into all_cards
+    fn remove_card(&mut self, index: usize) {
+        if index < self.board.len() {
+            self.board.remove(index);
+        } else {
+            self.bench.remove(index - self.board.len());
+        }
+    }
+
+    pub fn has_bench_space(&self) -> bool {
+        self.bench.len() < BENCH_SIZE as usize
+    }
+
+    /// Check whether a grid square is free on this player's board. ignore is a board index that is allowed to be there (for moves).
+    pub fn is_free(&self, grid_x: u16, grid_y: u16, ignore: Option<usize>) -> bool {
+        !self.board.iter().enumerate()
+            .any(|(i, unit)| Some(i) != ignore && unit.grid_x == grid_x && unit.grid_y == grid_y)
+    }
+}
+
+/// A unit on a player's saved board, at a grid square
+#[derive(Debug, PartialEq, AnchorDeserialize, AnchorSerialize, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BoardUnit {
+    pub card: Card,
+    pub grid_x: u16,
+    pub grid_y: u16,
+}
+
+/// Gold cost of buying a card. Units sell back for the same amount, so a merged unit is worth all three of its parts.
+pub fn card_cost(card: &Card) -> u8 {
+    let base: u8 = match card.rarity {
+        Rarity::Common => 1,
+        Rarity::Uncommon => 2,
+        Rarity::Rare => 3,
+        Rarity::Epic => 4,
+        Rarity::Legendary => 5,
+        Rarity::Mythic => 5,
+    };
+    base.saturating_mul(3u8.saturating_pow(card.star_level.saturating_sub(1) as u32))
+}
+
+/// Randomness used alongside the first reveal to generate a player's shop. `round_randomness` is the round's
+/// committed randomness, so shops can't be worked out before the round starts.
+pub fn shop_randomness(round_randomness: &[u8; 32], player: Controller, round: u8, rerolls: u8) -> [u8; 32] {
+    hashv(&[b"Shop", round_randomness, &[player as u8], &[round], &[rerolls]]).to_bytes()
+}
@@ -0,0 +1,143 @@
+use anchor_lang::prelude::*;
+use super::{units::{self, UnitType}, entities::{Entities, Controller, EntityState}, ruleset};
+
+/// Synergy tags shared between unit types. Fielding enough units with a tag gives every unit on that player's board a bonus.
+#[derive(Debug, Ord, Eq, PartialOrd, PartialEq, AnchorDeserialize, AnchorSerialize, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum SynergyTag {
+    Beast,
+    Tank,
+    Predator,
+}
+
+pub const ALL_SYNERGY_TAGS: [SynergyTag; 3] = [SynergyTag::Beast, SynergyTag::Tank, SynergyTag::Predator];
+
+pub fn get_synergy_tags(unit_type: UnitType) -> &'static [SynergyTag] {
+    match unit_type {
+        UnitType::Wolf => &[SynergyTag::Beast, SynergyTag::Predator],
+        UnitType::Bear => &[SynergyTag::Beast, SynergyTag::Tank],
+        UnitType::Bull => &[SynergyTag::Tank],
+        UnitType::Hawk => &[SynergyTag::Beast, SynergyTag::Predator],
+        UnitType::Hidden{..} => &[],
+    }
+}
+
+/// Percent increases to a unit's stats
+#[derive(Debug, Default, PartialEq, AnchorDeserialize, AnchorSerialize, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct SynergyBonus {
+    pub attack_damage: u16,
+    pub health: u16,
+    pub movement_speed: u16,
+    /// Added to the attack speed multiplier, from SPEED_SCALING_VERSION
+    pub attack_speed: u16,
+}
+
+/// Bonus tiers for a tag, as (units needed, bonus). Higher tiers replace lower ones.
+pub fn get_synergy_tiers(tag: SynergyTag) -> &'static [(u8, SynergyBonus)] {
+    match tag {
+        SynergyTag::Beast => &[
+            (2, SynergyBonus { attack_damage: 10, health: 0, movement_speed: 0, attack_speed: 0 }),
+            (4, SynergyBonus { attack_damage: 25, health: 0, movement_speed: 0, attack_speed: 0 }),
+        ],
+        SynergyTag::Tank => &[
+            (2, SynergyBonus { attack_damage: 0, health: 15, movement_speed: 0, attack_speed: 0 }),
+            (4, SynergyBonus { attack_damage: 0, health: 35, movement_speed: 0, attack_speed: 0 }),
+        ],
+        SynergyTag::Predator => &[
+            (2, SynergyBonus { attack_damage: 0, health: 0, movement_speed: 10, attack_speed: 0 }),
+            (3, SynergyBonus { attack_damage: 5, health: 0, movement_speed: 20, attack_speed: 15 }),
+        ],
+    }
+}
+
+/// A synergy a player has reached at least the first tier of
+#[derive(Debug, PartialEq, AnchorDeserialize, AnchorSerialize, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ActiveSynergy {
+    pub tag: SynergyTag,
+    /// Units on the board with the tag
+    pub count: u8,
+    /// Index into get_synergy_tiers
+    pub tier: u8,
+    pub bonus: SynergyBonus,
+}
+
+/// Work out which synergies a player has from their revealed, fielded units
+pub fn get_active_synergies(entities: &Entities, player: Controller) -> Vec<ActiveSynergy> {
+    let mut active = Vec::new();
+    for tag in ALL_SYNERGY_TAGS {
+        let count = entities.all.iter()
+            .filter(|entity| entity.owner == player && !entity.is_benched() && entity.state != EntityState::Dead)
+            .filter(|entity| get_synergy_tags(entity.unit_type).contains(&tag))
+            .count() as u8;
+        let reached = get_synergy_tiers(tag).iter().enumerate().rev().find(|(_, (needed, _))| count >= *needed);
+        if let Some((tier, (_, bonus))) = reached {
+            active.push(ActiveSynergy { tag, count, tier: tier as u8, bonus: *bonus });
+        }
+    }
+    active
+}
+
+fn add_percent(value: u16, percent: u16) -> u16 {
+    (value as u32 * (100 + percent as u32) / 100).min(u16::MAX as u32) as u16
+}
+
+/// Apply each player's active synergies to the stats of all their fielded units. Called once at battle start.
+/// From SPEED_SCALING_VERSION this also sets the base attack speed multiplier, starting from the unit's rarity.
+pub fn apply_synergies(entities: &mut Entities, ruleset_version: u16) {
+    let scale_speed = ruleset_version >= ruleset::SPEED_SCALING_VERSION;
+    if scale_speed {
+        for entity in entities.all.iter_mut().filter(|entity| !entity.is_benched()) {
+            if let Some(rarity) = entity.rarity {
+                entity.base_attack_speed_multiplier = units::get_rarity_attack_speed(rarity);
+            }
+        }
+    }
+    for player in [Controller::Initializer, Controller::Opponent] {
+        let active = get_active_synergies(entities, player);
+        for synergy in active {
+            for entity in entities.all.iter_mut().filter(|entity| entity.owner == player && !entity.is_benched()) {
+                if let Some(stats) = entity.stats.as_mut() {
+                    stats.attack_damage = add_percent(stats.attack_damage, synergy.bonus.attack_damage);
+                    stats.starting_health = add_percent(stats.starting_health, synergy.bonus.health);
+                    stats.movement_speed = add_percent(stats.movement_speed, synergy.bonus.movement_speed);
+                    entity.health = add_percent(entity.health, synergy.bonus.health);
+                    entity.max_health = add_percent(entity.max_health, synergy.bonus.health);
+                    if scale_speed {
+                        entity.base_attack_speed_multiplier = add_percent(entity.base_attack_speed_multiplier, synergy.bonus.attack_speed);
+                    }
+                }
+            }
+        }
+    }
+    if scale_speed {
+        for entity in &mut entities.all {
+            entity.update_multipliers();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{units::{self, Rarity}, ruleset::CURRENT_RULESET_VERSION};
+
+    #[test]
+    fn synergies_boost_the_whole_board() {
+        let mut entities = Entities::default();
+        entities.create(Controller::Initializer, 100, 100, units::test_card(UnitType::Wolf, None));
+        entities.create(Controller::Initializer, 200, 100, units::test_card(UnitType::Bear, None));
+        let bull = entities.create(Controller::Initializer, 300, 100, units::test_card(UnitType::Bull, None));
+        let enemy = entities.create(Controller::Opponent, 300, 300, units::test_card(UnitType::Wolf, None));
+
+        let active = get_active_synergies(&entities, Controller::Initializer);
+        // wolf + bear are beasts, bear + bull are tanks, one wolf is not enough for predator
+        assert_eq!(active.iter().map(|synergy| synergy.tag).collect::<Vec<_>>(), vec![SynergyTag::Beast, SynergyTag::Tank]);
+        assert!(get_active_synergies(&entities, Controller::Opponent).is_empty());
+
+        apply_synergies(&mut entities, ruleset::CURRENT_RULESET_VERSION);
+        let bull_stats = units::get_baseline_unit_stats(CURRENT_RULESET_VERSION, UnitType::Bull, Rarity::Common).unwrap();
+        let bull_entity = entities.get_by_id_mut(bull).unwrap();
+        assert_eq!(bull_entity.stats.as_ref().unwrap().attack_damage, bull_stats.attack_damage * 110 / 100);
+        assert_eq!(bull_entity.health, bull_stats.starting_health * 115 / 100);
+        assert_eq!(entities.get_by_id_mut(enemy).unwrap().stats, Some(units::get_baseline_unit_stats(CURRENT_RULESET_VERSION, UnitType::Wolf, Rarity::Common).unwrap()));
+    }
+}
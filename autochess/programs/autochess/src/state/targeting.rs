@@ -0,0 +1,99 @@
+use super::{entities::{Entities, Entity, EntityResult, EntityState, Controller}, units::{UnitType, SpecialTrait}, ruleset};
+
+/// How a unit picks who to attack. A target is kept until it dies or becomes untargetable, except with Retarget.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TargetingPolicy {
+    /// Closest enemy
+    Closest,
+    /// Closest enemy, switching whenever a closer one comes into attack range
+    Retarget,
+    LowestHealth,
+    /// Enemy with the most attack damage
+    HighestDamage,
+    /// Enemy furthest back on their own side of the board
+    Backline,
+}
+
+pub fn get_unit_targeting(unit_type: UnitType) -> TargetingPolicy {
+    match unit_type {
+        UnitType::Wolf => TargetingPolicy::Retarget,
+        UnitType::Bull => TargetingPolicy::HighestDamage,
+        UnitType::Hawk => TargetingPolicy::LowestHealth,
+        _ => TargetingPolicy::Closest,
+    }
+}
+
+/// Traits that change targeting take priority over the unit type
+pub fn get_trait_targeting(special_trait: SpecialTrait) -> Option<TargetingPolicy> {
+    match special_trait {
+        SpecialTrait::Assassin => Some(TargetingPolicy::Backline),
+        _ => None,
+    }
+}
+
+/// Policy for an entity under a ruleset. Everything targeted the closest enemy before TARGETING_VERSION.
+pub fn get_targeting_policy(entity: &Entity, ruleset_version: u16) -> TargetingPolicy {
+    if ruleset_version < ruleset::TARGETING_VERSION {
+        return TargetingPolicy::Closest;
+    }
+    entity.special_trait
+        .and_then(get_trait_targeting)
+        .unwrap_or_else(|| get_unit_targeting(entity.unit_type))
+}
+
+/// Pick a target for `seeker` among targetable enemies on the board. Each policy ranks by its own measure first,
+/// then by distance, then by id, so the choice never depends on the order entities are stored in.
+pub fn find_target<'a>(entities: &'a Entities, seeker: &Entity, policy: TargetingPolicy) -> Option<EntityResult<'a>> {
+    let enemy = seeker.get_enemy();
+    let mut best: Option<((u16, u16, u16), EntityResult<'a>)> = None;
+    for other in &entities.all {
+        if other.owner != enemy || other.is_benched() || other.is_untargetable() || other.state == EntityState::Dead {
+            continue;
+        }
+        let distance = seeker.position.distance(&other.position);
+        let rank = match policy {
+            TargetingPolicy::Closest | TargetingPolicy::Retarget => 0,
+            TargetingPolicy::LowestHealth => other.health,
+            TargetingPolicy::HighestDamage => u16::MAX - other.stats.as_ref().map_or(0, |stats| stats.attack_damage),
+            // initializers start at the top of the board, opponents at the bottom
+            TargetingPolicy::Backline => match other.owner {
+                Controller::Initializer => other.position.y,
+                _ => u16::MAX - other.position.y,
+            },
+        };
+        let key = (rank, distance, other.id);
+        if best.as_ref().map_or(true, |(best_key, _)| key < *best_key) {
+            best = Some((key, EntityResult { entity: other, distance }));
+        }
+    }
+    best.map(|(_, result)| result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::units;
+
+    #[test]
+    fn policies_pick_different_targets() {
+        let mut entities = Entities::default();
+        let seeker = entities.create(Controller::Initializer, 400, 100, units::test_card(UnitType::Wolf, None));
+        let close = entities.create(Controller::Opponent, 400, 450, units::test_card(UnitType::Bear, None));
+        let wounded = entities.create(Controller::Opponent, 100, 550, units::test_card(UnitType::Wolf, None));
+        let back = entities.create(Controller::Opponent, 700, 750, units::test_card(UnitType::Hawk, None));
+        let strong = entities.create(Controller::Opponent, 700, 450, units::test_card(UnitType::Bull, None));
+        entities.get_by_id_mut(wounded).unwrap().health = 1;
+        for id in [close, back, strong] {
+            let stats = entities.get_by_id(id).unwrap().stats.clone().unwrap();
+            entities.get_by_id_mut(id).unwrap().stats = Some(units::UnitStats { attack_damage: if id == strong { 50 } else { 1 }, ..stats });
+        }
+        entities.get_by_id_mut(wounded).unwrap().stats.as_mut().unwrap().attack_damage = 1;
+
+        let seeker = entities.get_by_id(seeker).unwrap().clone();
+        let pick = |policy| find_target(&entities, &seeker, policy).unwrap().entity.id;
+        assert_eq!(pick(TargetingPolicy::Closest), close);
+        assert_eq!(pick(TargetingPolicy::LowestHealth), wounded);
+        assert_eq!(pick(TargetingPolicy::HighestDamage), strong);
+        assert_eq!(pick(TargetingPolicy::Backline), back);
+    }
+}
@@ -0,0 +1,111 @@
+//! Seeded battles with their outcomes pinned per ruleset version. The same hashes must come out on every target
+//! (native, wasm, BPF); if one changes on any of them the client preview no longer matches the chain.
+//! A pinned hash never changes. Bumping the ruleset version means pinning the new version's hash here.
+
+use anchor_lang::{prelude::*, solana_program::hash::hash};
+use super::{game::{Game, WinCondition, draw_hand}, entities};
+
+/// Battles played per ruleset version
+pub const SEEDED_BATTLES: u32 = 100;
+
+/// Outcome hash of the seeded battles for each ruleset version, starting at 1
+pub const PINNED_OUTCOMES: [(u16, &str); 17] = [
+    (1, "J2iA23bLWNW3xnRyAzc3dyqC9FHtRVEkaUxvsDqXgeor"),
+    (2, "3RbVM7Z9yY6dnrpJpaZxs63vwnktRyxqBPz21TWfhptT"),
+    (3, "5SRTFZfmMhgWL58GPjaLQh2tGFNMKaCqTd2giyvtv11g"),
+    (4, "5Yx1dSXqNzh2Uhu6nt9yDyBiLh3jPm9eezLTQkfo5rsH"),
+    (5, "GirFFqozq5zcwVLA7RA3njTiRMzSWyTfzQFbbSMGZhEB"),
+    (6, "EWayqaByJYJ8bYtogYJK7mnsejM94GSPS7B4szTdqoNP"),
+    (7, "tnp2QTt2opyxBZHFSFmvrUxZey1Kbo6gpKPBnHk2vuu"),
+    (8, "6pR9K9jrSvsaqiv8fPEDT9evpEqPfN9WZHJX3hqwWBe7"),
+    (9, "GS5gET6YnsqkaotiobPArB54iWBHGe6GBnfxdXcj5Zt7"),
+    (10, "65U3qDMjSLeptZYrpsbebvAj4bKWfRjQjGJvx7q9M8hP"),
+    (11, "2k68faUzk9LizaiSmAUEehuy6A9h4H6JkPtm9AVTgswa"),
+    (12, "GpJ1axWdsg89FxLAHJSHoJs5ruPhfA37yfuFKAFUWuJM"),
+    (13, "Gk8SVH8FarkjnZxAF4kyMMYxSDmhQbWTRYFrfjGgcckM"),
+    (14, "GFAL1XXDeJpYFR2itRXgUAEEbAzuCN5KtXEu69Lhi2CU"),
+    (15, "5FxH6irPeCkv9wRcdaALCzvjvbSPeaGAPNXHu5pgaz8T"),
+    (16, "6pf3VtbNfX4ZGL9MZsEb6hZUaUPwd4U8RJiSuQajTfpY"),
+    (17, "8RAD7LXY7CeAqz9chLc5YpsH2VbGEoSxaDyy62gumX2X"),
+];
+
+/// Play out a battle with boards and crits drawn from a seed
+pub fn run_seeded_battle(seed: u32, ruleset_version: u16) -> Game {
+    let mut game = Game::new_client();
+    game.ruleset_version = ruleset_version;
+    let randomness = hash(&seed.to_le_bytes()).to_bytes();
+    game.reveal_2 = Some(randomness);
+    for (player, first_row) in [(entities::Controller::Initializer, 0), (entities::Controller::Opponent, 6)] {
+        let hand = draw_hand(4, &randomness, &[player as u8; 32], &game.ruleset());
+        for (i, card) in hand.into_iter().enumerate() {
+            let grid_x = (randomness[i + 4 * player as usize] % 8) as u16;
+            let grid_y = first_row + (i % 2) as u16;
+            game.entities.create(player, grid_x * 100 + 50, grid_y * 100 + 50, card);
+        }
+    }
+    for _ in 0..300 {
+        game.step();
+        game.update_win_condition();
+        if game.win_condition != WinCondition::InProgress {
+            break;
+        }
+    }
+    game
+}
+
+/// What decides a battle: the tick it stopped on, the winner, and each unit's id, position, health and state.
+/// Leaves out everything else so adding a field to an entity doesn't change the pinned hashes.
+pub fn outcome_bytes(game: &Game) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend(game.tick.to_le_bytes());
+    bytes.extend(game.win_condition.try_to_vec().unwrap());
+    for entity in &game.entities.all {
+        bytes.extend(entity.id.to_le_bytes());
+        bytes.extend(entity.position.x.to_le_bytes());
+        bytes.extend(entity.position.y.to_le_bytes());
+        bytes.extend(entity.health.to_le_bytes());
+        bytes.extend(entity.state.try_to_vec().unwrap());
+    }
+    bytes
+}
+
+/// Hash of the outcomes of the seeded battles under a ruleset version
+pub fn seeded_outcome_hash(ruleset_version: u16) -> String {
+    let mut outcomes = Vec::new();
+    for seed in 0..SEEDED_BATTLES {
+        outcomes.extend(outcome_bytes(&run_seeded_battle(seed, ruleset_version)));
+    }
+    hash(&outcomes).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{ruleset, game::BATTLE_TICK_LIMIT};
+
+    #[test]
+    fn seeded_battles_are_reproducible() {
+        assert_eq!(PINNED_OUTCOMES.len(), ruleset::CURRENT_RULESET_VERSION as usize, "pin the new ruleset version");
+        for (ruleset_version, pinned) in PINNED_OUTCOMES {
+            assert_eq!(seeded_outcome_hash(ruleset_version), pinned, "ruleset version {}", ruleset_version);
+        }
+    }
+
+    /// Battles still going after the seeded run are played on to the tick limit, where later versions decide them
+    fn finishes(seed: u32, ruleset_version: u16) -> bool {
+        let mut game = run_seeded_battle(seed, ruleset_version);
+        while game.win_condition == WinCondition::InProgress && game.tick < BATTLE_TICK_LIMIT {
+            game.step();
+            game.update_win_condition();
+        }
+        game.win_condition != WinCondition::InProgress
+    }
+
+    #[test]
+    fn seeded_battles_finish() {
+        for ruleset_version in 1..=ruleset::CURRENT_RULESET_VERSION {
+            let finished = (0..SEEDED_BATTLES).filter(|seed| finishes(*seed, ruleset_version)).count();
+            assert!(finished as u32 > SEEDED_BATTLES * 9 / 10, "ruleset version {}: only {} battles finished", ruleset_version, finished);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! Special traits hook into a unit's life in battle and respond by queuing actions, the same way abilities do.
+//! Game::step raises the events; a trait only has to handle the ones it cares about.
+
+use super::{entities::{Entities, Entity, EntityBuff}, actions::{Action, Actions}, units::{SpecialTrait, DamageType}, ruleset};
+
+/// Berserker attacks deal up to this much extra damage, in percent, scaling with missing health
+const BERSERKER_MAX_BONUS: u32 = 100;
+/// Attack speed Berserker gains for a while after a kill
+const BERSERKER_KILL_ATTACK_SPEED: u16 = 30;
+const BERSERKER_KILL_DURATION: u16 = 20;
+/// Guardian shields allies within this range for a share of its own health
+const GUARDIAN_RANGE: u16 = 150;
+const GUARDIAN_SHIELD_PERCENT: u16 = 20;
+const GUARDIAN_SHIELD_DURATION: u16 = 100;
+/// Percent of attack damage healed by Vampiric. From HEALING_VERSION it's a lifesteal buff for the whole battle,
+/// which also covers ability damage.
+const VAMPIRIC_LIFESTEAL_PERCENT: u16 = 25;
+/// Percent of basic attack damage Thorns sends back to the attacker
+const THORNS_REFLECT_PERCENT: u16 = 25;
+/// How far Swift dashes at the enemy on the first tick
+const SWIFT_DASH_DISTANCE: u16 = 200;
+/// From TRAIT_REACTIONS_VERSION, damage from Swift slows the target by this much for a while
+const SWIFT_SLOW_PERCENT: u16 = 20;
+const SWIFT_SLOW_DURATION: u16 = 10;
+/// From TRAIT_REACTIONS_VERSION, Assassin can't be targeted for this long after a critical hit
+const ASSASSIN_VANISH_DURATION: u16 = 10;
+
+/// Points in a battle where traits can act
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TraitEvent {
+    /// Tick 0, before anyone moves
+    BattleStart,
+    /// Every tick after the first while the unit is alive
+    Tick,
+    /// A basic attack by this unit landed
+    Attack{target_id: u16, damage: u16},
+    /// This unit was hit by a basic attack
+    Hit{attacker_id: u16, damage: u16},
+    /// This unit killed another. Before EVENTS_VERSION every unit that landed a basic attack on the victim that tick got the kill.
+    Kill{victim_id: u16},
+    /// This unit died this tick
+    Death,
+    /// This unit landed a critical hit
+    Crit{target_id: u16, damage: u16},
+    /// Damage from this unit got through to another
+    DamageDealt{target_id: u16, amount: u16},
+}
+
+/// Queue the actions a trait takes in response to an event. `entity` is the unit with the trait as it was when the
+/// event happened, and `all_entities` the board to look for allies and enemies on.
+pub fn trigger(special_trait: SpecialTrait, event: TraitEvent, entity: &Entity, all_entities: &Entities, actions: &mut Actions, ruleset_version: u16) {
+    let lifesteal_stat = ruleset_version >= ruleset::HEALING_VERSION;
+    let reactions = ruleset_version >= ruleset::TRAIT_REACTIONS_VERSION;
+    match (special_trait, event) {
+        (SpecialTrait::Assassin, TraitEvent::BattleStart) => {
+            entity.assassin_hop(actions);
+        },
+        (SpecialTrait::Swift, TraitEvent::BattleStart) => {
+            let stats = entity.stats.as_ref().unwrap();
+            if let Some(result) = all_entities.find_closest_entity(&entity.position, entity.get_enemy()) {
+                let dash = SWIFT_DASH_DISTANCE.min(result.distance.saturating_sub(stats.attack_range));
+                if dash > 0 {
+                    let to = entity.position.move_towards(&result.entity.position, result.distance, dash);
+                    actions.add(entity.id, Action::Move{to});
+                }
+            }
+        },
+        (SpecialTrait::Assassin, TraitEvent::Crit{..}) if reactions => {
+            actions.add(entity.id, Action::ApplyBuff{buff: EntityBuff::Untargetable{duration: ASSASSIN_VANISH_DURATION}});
+        },
+        (SpecialTrait::Swift, TraitEvent::DamageDealt{target_id, amount}) if reactions && amount > 0 => {
+            actions.add(target_id, Action::ApplyBuff{buff: EntityBuff::Slow{duration: SWIFT_SLOW_DURATION, percent: SWIFT_SLOW_PERCENT}});
+        },
+        (SpecialTrait::Guardian, TraitEvent::BattleStart) | (SpecialTrait::Guardian, TraitEvent::Death) => {
+            shield_allies(entity, all_entities, actions);
+        },
+        (SpecialTrait::Berserker, TraitEvent::Attack{target_id, damage}) => {
+            let max_health = entity.stats.as_ref().map_or(0, |stats| stats.starting_health) as u32;
+            if max_health > 0 {
+                let missing = max_health.saturating_sub(entity.health as u32);
+                let bonus = damage as u32 * BERSERKER_MAX_BONUS * missing / (100 * max_health);
+                if bonus > 0 {
+                    actions.add(target_id, Action::Damage{amount: bonus as u16, source: Some(entity.id), crit: false, damage_type: DamageType::Physical});
+                }
+            }
+        },
+        (SpecialTrait::Berserker, TraitEvent::Kill{..}) => {
+            actions.add(entity.id, Action::ApplyBuff{buff: EntityBuff::AttackSpeed{duration: BERSERKER_KILL_DURATION, percent: BERSERKER_KILL_ATTACK_SPEED}});
+        },
+        (SpecialTrait::Vampiric, TraitEvent::BattleStart) if lifesteal_stat => {
+            actions.add(entity.id, Action::ApplyBuff{buff: EntityBuff::Lifesteal{duration: u16::MAX, percent: VAMPIRIC_LIFESTEAL_PERCENT}});
+        },
+        (SpecialTrait::Vampiric, TraitEvent::Attack{damage, ..}) if !lifesteal_stat => {
+            let amount = (damage as u32 * VAMPIRIC_LIFESTEAL_PERCENT as u32 / 100) as u16;
+            if amount > 0 {
+                actions.add(entity.id, Action::Heal{amount});
+            }
+        },
+        (SpecialTrait::Thorns, TraitEvent::Hit{attacker_id, damage}) => {
+            let amount = (damage as u32 * THORNS_REFLECT_PERCENT as u32 / 100).max(1) as u16;
+            actions.add(attacker_id, Action::Damage{amount, source: Some(entity.id), crit: false, damage_type: DamageType::True});
+        },
+        _ => {}
+    }
+}
+
+fn shield_allies(entity: &Entity, all_entities: &Entities, actions: &mut Actions) {
+    let health = entity.stats.as_ref().map_or(0, |stats| stats.starting_health);
+    let amount = (health as u32 * GUARDIAN_SHIELD_PERCENT as u32 / 100) as u16;
+    for ally in all_entities.find_all_entities(&entity.position, GUARDIAN_RANGE, entity.owner) {
+        if ally.id != entity.id {
+            actions.add(ally.id, Action::ApplyBuff{buff: EntityBuff::Shield{duration: GUARDIAN_SHIELD_DURATION, amount}});
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{entities::Controller, units::{self, UnitType}, ruleset};
+
+    #[test]
+    fn traits_respond_to_their_events() {
+        let mut entities = Entities::default();
+        let berserker = entities.create(Controller::Initializer, 400, 300, units::test_card(UnitType::Wolf, Some(SpecialTrait::Berserker)));
+        let guardian = entities.create(Controller::Initializer, 500, 300, units::test_card(UnitType::Wolf, Some(SpecialTrait::Guardian)));
+        let enemy = entities.create(Controller::Opponent, 400, 400, units::test_card(UnitType::Wolf, Some(SpecialTrait::Thorns)));
+        let max_health = entities.get_by_id(berserker).unwrap().health;
+        entities.get_by_id_mut(berserker).unwrap().health = max_health / 2;
+
+        let mut actions = Actions::new();
+        let berserker_entity = entities.get_by_id(berserker).unwrap();
+        trigger(SpecialTrait::Berserker, TraitEvent::Attack{target_id: enemy, damage: 20}, berserker_entity, &entities, &mut actions, ruleset::CURRENT_RULESET_VERSION);
+        assert!(matches!(actions.get_actions_by_id(&enemy).unwrap()[..], [Action::Damage{amount: 10, ..}]));
+
+        // events a trait doesn't handle do nothing
+        trigger(SpecialTrait::Berserker, TraitEvent::Tick, berserker_entity, &entities, &mut actions, ruleset::CURRENT_RULESET_VERSION);
+        trigger(SpecialTrait::Thorns, TraitEvent::Hit{attacker_id: berserker, damage: 20}, entities.get_by_id(enemy).unwrap(), &entities, &mut actions, ruleset::CURRENT_RULESET_VERSION);
+        assert!(matches!(actions.get_actions_by_id(&berserker).unwrap()[..], [Action::Damage{amount: 5, ..}]));
+
+        trigger(SpecialTrait::Guardian, TraitEvent::BattleStart, entities.get_by_id(guardian).unwrap(), &entities, &mut actions, ruleset::CURRENT_RULESET_VERSION);
+        assert!(matches!(actions.get_actions_by_id(&berserker).unwrap()[..], [_, Action::ApplyBuff{buff: EntityBuff::Shield{..}}]));
+        assert!(actions.get_actions_by_id(&guardian).is_none());
+    }
+
+    #[test]
+    fn traits_react_to_crits_and_damage_dealt() {
+        let mut entities = Entities::default();
+        let assassin = entities.create(Controller::Initializer, 400, 300, units::test_card(UnitType::Wolf, Some(SpecialTrait::Assassin)));
+        let swift = entities.create(Controller::Initializer, 500, 300, units::test_card(UnitType::Wolf, Some(SpecialTrait::Swift)));
+        let enemy = entities.create(Controller::Opponent, 400, 400, units::test_card(UnitType::Wolf, None));
+
+        for (ruleset_version, reacts) in [(ruleset::TRAIT_REACTIONS_VERSION - 1, false), (ruleset::TRAIT_REACTIONS_VERSION, true)] {
+            let mut actions = Actions::new();
+            trigger(SpecialTrait::Assassin, TraitEvent::Crit{target_id: enemy, damage: 40}, entities.get_by_id(assassin).unwrap(), &entities, &mut actions, ruleset_version);
+            trigger(SpecialTrait::Swift, TraitEvent::DamageDealt{target_id: enemy, amount: 20}, entities.get_by_id(swift).unwrap(), &entities, &mut actions, ruleset_version);
+            // nothing got through, nothing to slow
+            trigger(SpecialTrait::Swift, TraitEvent::DamageDealt{target_id: assassin, amount: 0}, entities.get_by_id(swift).unwrap(), &entities, &mut actions, ruleset_version);
+            if reacts {
+                assert!(matches!(actions.get_actions_by_id(&assassin).unwrap()[..], [Action::ApplyBuff{buff: EntityBuff::Untargetable{..}}]));
+                assert!(matches!(actions.get_actions_by_id(&enemy).unwrap()[..], [Action::ApplyBuff{buff: EntityBuff::Slow{..}}]));
+            } else {
+                assert!(actions.get_actions_by_id(&assassin).is_none());
+                assert!(actions.get_actions_by_id(&enemy).is_none());
+            }
+        }
+    }
+}
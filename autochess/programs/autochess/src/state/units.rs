@@ -1,42 +1,13 @@
 use anchor_lang::{prelude::*};
 use std::collections::BTreeMap;
+use super::{registry, ruleset::{Ruleset, CURRENT_RULESET_VERSION, RANGED_UNITS_VERSION, MITIGATION_VERSION}};
 
+/// Common stats for each unit type, read from the same table as get_baseline_unit_stats
 pub fn get_unit_map() -> BTreeMap<UnitType, UnitStats> {
     let mut units = BTreeMap::new();
-    const TICKS_PER_SECOND: u16 = 5;
-    units.insert(
-        UnitType::Wolf,
-        UnitStats {
-            movement_speed: 200 / TICKS_PER_SECOND, // per tick
-            attack_duration: 4, // in ticks
-            attack_range: 125,
-            attack_damage: 15,
-            starting_health: 100,
-            crit_chance: 50, // out of 255
-        },
-    );
-    units.insert(
-        UnitType::Bear,
-        UnitStats {
-            movement_speed: 75 / TICKS_PER_SECOND, // per tick
-            attack_duration: 6,
-            attack_range: 150,
-            attack_damage: 10,
-            starting_health: 250,
-            crit_chance: 20, // out of 255
-        },
-    );
-    units.insert(
-        UnitType::Bull,
-        UnitStats {
-            movement_speed: 125 / TICKS_PER_SECOND, // per tick
-            attack_duration: 7,
-            attack_range: 150,
-            attack_damage: 25,
-            starting_health: 150,
-            crit_chance: 20, // out of 255
-        },
-    );
+    for unit_type in registry::REGISTRY_UNIT_TYPES {
+        units.insert(unit_type, get_baseline_unit_stats(CURRENT_RULESET_VERSION, unit_type, Rarity::Common).unwrap());
+    }
     units
 }
 
@@ -46,6 +17,8 @@ pub enum UnitType {
     Bull,
     Bear,
     Hidden{hand_position: u8}, // 0 -> n-1 where n is size of hand
+    /// Ranged. Added after Hidden so existing variants keep their encoding.
+    Hawk,
 }
 
 impl Default for UnitType {
@@ -58,6 +31,25 @@ pub enum AttackType {
     Ranged{speed: u16},
 }
 
+/// Attack speed multiplier from rarity, where 100 = 1x
+pub fn get_rarity_attack_speed(rarity: Rarity) -> u16 {
+    match rarity {
+        Rarity::Common => 100,
+        Rarity::Uncommon => 102,
+        Rarity::Rare => 104,
+        Rarity::Epic => 106,
+        Rarity::Legendary => 108,
+        Rarity::Mythic => 110,
+    }
+}
+
+pub fn get_attack_type(unit_type: UnitType) -> AttackType {
+    match unit_type {
+        UnitType::Hawk => AttackType::Ranged{speed: 60},
+        _ => AttackType::Melee,
+    }
+}
+
 #[derive(Debug, Ord, Eq, PartialOrd, PartialEq, AnchorDeserialize, AnchorSerialize, Clone, serde::Serialize, serde::Deserialize)]
 pub struct UnitStats {
     pub movement_speed: u16,
@@ -66,6 +58,34 @@ pub struct UnitStats {
     pub attack_damage: u16,
     pub starting_health: u16,
     pub crit_chance: u8,
+    /// Reduces physical damage. Damage taken is scaled by 100 / (100 + armor).
+    #[serde(default)]
+    pub armor: u16,
+    /// Reduces magic damage the same way armor reduces physical damage
+    #[serde(default)]
+    pub magic_resist: u16,
+}
+
+/// How damage is mitigated. Crits double damage before mitigation.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DamageType {
+    /// Basic attacks, reduced by armor
+    Physical,
+    /// Abilities, reduced by magic resist
+    Magic,
+    /// Ignores armor and magic resist
+    True,
+}
+
+/// Armor and magic resist of a unit type from MITIGATION_VERSION
+fn get_unit_resistances(unit_type: UnitType) -> (u16, u16) {
+    match unit_type {
+        UnitType::Bear => (40, 20),
+        UnitType::Bull => (30, 10),
+        UnitType::Wolf => (15, 15),
+        UnitType::Hawk => (5, 30),
+        UnitType::Hidden{..} => (0, 0),
+    }
 }
 
 #[derive(Debug, Ord, Eq, PartialOrd, PartialEq, AnchorDeserialize, AnchorSerialize, Clone, Copy, serde::Serialize, serde::Deserialize)]
@@ -78,7 +98,27 @@ pub enum Rarity {
     Mythic
 }
 
-pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<UnitStats> {
+/// Stats of a 1 star unit under a ruleset version. None for hidden units and unknown versions.
+pub fn get_baseline_unit_stats(ruleset_version: u16, unit_type: UnitType, rarity: Rarity) -> Option<UnitStats> {
+    match ruleset_version {
+        // the first ruleset's stats, with hawks from RANGED_UNITS_VERSION and armor and magic resist from MITIGATION_VERSION
+        1..=CURRENT_RULESET_VERSION => {
+            if unit_type == UnitType::Hawk && ruleset_version < RANGED_UNITS_VERSION {
+                return None;
+            }
+            let mut stats = get_unit_stats_v1(unit_type, rarity)?;
+            if ruleset_version >= MITIGATION_VERSION {
+                let (armor, magic_resist) = get_unit_resistances(unit_type);
+                stats.armor = armor;
+                stats.magic_resist = magic_resist;
+            }
+            Some(stats)
+        },
+        _ => None,
+    }
+}
+
+fn get_unit_stats_v1(unit_type: UnitType, rarity: Rarity) -> Option<UnitStats> {
     const TICKS_PER_SECOND: u16 = 5;
     match unit_type {
         UnitType::Wolf => Some(match rarity {
@@ -89,6 +129,8 @@ pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<Un
                 attack_damage: 15,
                 starting_health: 100,
                 crit_chance: 22, // out of 255
+                armor: 0,
+                magic_resist: 0,
             },
             Rarity::Uncommon => UnitStats {
                 movement_speed: 260 / TICKS_PER_SECOND, // per tick
@@ -97,6 +139,8 @@ pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<Un
                 attack_damage: 16,
                 starting_health: 105,
                 crit_chance: 26, // out of 255
+                armor: 0,
+                magic_resist: 0,
             }, 
             Rarity::Rare => UnitStats {
                 movement_speed: 270 / TICKS_PER_SECOND, // per tick
@@ -105,6 +149,8 @@ pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<Un
                 attack_damage: 18,
                 starting_health: 110,
                 crit_chance: 30, // out of 255
+                armor: 0,
+                magic_resist: 0,
             },
             Rarity::Epic => UnitStats {
                 movement_speed: 280 / TICKS_PER_SECOND, // per tick
@@ -113,6 +159,8 @@ pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<Un
                 attack_damage: 19,
                 starting_health: 115,
                 crit_chance: 35, // out of 255
+                armor: 0,
+                magic_resist: 0,
             },
             Rarity::Legendary => UnitStats {
                 movement_speed: 290 / TICKS_PER_SECOND, // per tick
@@ -121,6 +169,8 @@ pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<Un
                 attack_damage: 20,
                 starting_health: 120,
                 crit_chance: 37, // out of 255
+                armor: 0,
+                magic_resist: 0,
             },
             Rarity::Mythic => UnitStats {
                 movement_speed: 300 / TICKS_PER_SECOND, // per tick
@@ -129,6 +179,8 @@ pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<Un
                 attack_damage: 21,
                 starting_health: 125,
                 crit_chance: 42, // out of 255
+                armor: 0,
+                magic_resist: 0,
             },
         }),
         UnitType::Bull => Some(match rarity {
@@ -139,6 +191,8 @@ pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<Un
                 attack_damage: 20,
                 starting_health: 150,
                 crit_chance: 15, // out of 255
+                armor: 0,
+                magic_resist: 0,
             },
             Rarity::Uncommon => UnitStats {
                 movement_speed: 125 / TICKS_PER_SECOND, // per tick
@@ -147,6 +201,8 @@ pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<Un
                 attack_damage: 22,
                 starting_health: 155,
                 crit_chance: 17, // out of 255
+                armor: 0,
+                magic_resist: 0,
             }, 
             Rarity::Rare => UnitStats {
                 movement_speed: 125 / TICKS_PER_SECOND, // per tick
@@ -155,6 +211,8 @@ pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<Un
                 attack_damage: 25,
                 starting_health: 160,
                 crit_chance: 17, // out of 255
+                armor: 0,
+                magic_resist: 0,
             },
             Rarity::Epic => UnitStats {
                 movement_speed: 125 / TICKS_PER_SECOND, // per tick
@@ -163,6 +221,8 @@ pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<Un
                 attack_damage: 27,
                 starting_health: 165,
                 crit_chance: 17, // out of 255
+                armor: 0,
+                magic_resist: 0,
             },
             Rarity::Legendary => UnitStats {
                 movement_speed: 125 / TICKS_PER_SECOND, // per tick
@@ -171,6 +231,8 @@ pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<Un
                 attack_damage: 29,
                 starting_health: 170,
                 crit_chance: 17, // out of 255
+                armor: 0,
+                magic_resist: 0,
             },
             Rarity::Mythic => UnitStats {
                 movement_speed: 125 / TICKS_PER_SECOND, // per tick
@@ -179,6 +241,8 @@ pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<Un
                 attack_damage: 32,
                 starting_health: 175,
                 crit_chance: 17, // out of 255
+                armor: 0,
+                magic_resist: 0,
             },
         }),
         UnitType::Bear => Some(match rarity {
@@ -189,6 +253,8 @@ pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<Un
                 attack_damage: 8,
                 starting_health: 230,
                 crit_chance: 15, // out of 255
+                armor: 0,
+                magic_resist: 0,
             },
             Rarity::Uncommon => UnitStats {
                 movement_speed: 75 / TICKS_PER_SECOND, // per tick
@@ -197,6 +263,8 @@ pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<Un
                 attack_damage: 10,
                 starting_health: 250,
                 crit_chance: 15, // out of 255
+                armor: 0,
+                magic_resist: 0,
             },
             Rarity::Rare => UnitStats {
                 movement_speed: 75 / TICKS_PER_SECOND, // per tick
@@ -205,6 +273,8 @@ pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<Un
                 attack_damage: 12,
                 starting_health: 270,
                 crit_chance: 15, // out of 255
+                armor: 0,
+                magic_resist: 0,
             },
             Rarity::Epic => UnitStats {
                 movement_speed: 75 / TICKS_PER_SECOND, // per tick
@@ -213,6 +283,8 @@ pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<Un
                 attack_damage: 14,
                 starting_health: 290,
                 crit_chance: 15, // out of 255
+                armor: 0,
+                magic_resist: 0,
             },
             Rarity::Legendary => UnitStats {
                 movement_speed: 75 / TICKS_PER_SECOND, // per tick
@@ -221,6 +293,8 @@ pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<Un
                 attack_damage: 15,
                 starting_health: 310,
                 crit_chance: 15, // out of 255
+                armor: 0,
+                magic_resist: 0,
             },
             Rarity::Mythic => UnitStats {
                 movement_speed: 75 / TICKS_PER_SECOND, // per tick
@@ -229,12 +303,100 @@ pub fn get_baseline_unit_stats(unit_type: UnitType, rarity: Rarity) -> Option<Un
                 attack_damage: 16,
                 starting_health: 330,
                 crit_chance: 15, // out of 255
+                armor: 0,
+                magic_resist: 0,
+            },
+        }),
+        UnitType::Hawk => Some(match rarity {
+            Rarity::Common => UnitStats {
+                movement_speed: 150 / TICKS_PER_SECOND, // per tick
+                attack_duration: 5, // in ticks
+                attack_range: 400,
+                attack_damage: 12,
+                starting_health: 80,
+                crit_chance: 20, // out of 255
+                armor: 0,
+                magic_resist: 0,
+            },
+            Rarity::Uncommon => UnitStats {
+                movement_speed: 150 / TICKS_PER_SECOND, // per tick
+                attack_duration: 5, // in ticks
+                attack_range: 410,
+                attack_damage: 13,
+                starting_health: 84,
+                crit_chance: 23, // out of 255
+                armor: 0,
+                magic_resist: 0,
+            },
+            Rarity::Rare => UnitStats {
+                movement_speed: 160 / TICKS_PER_SECOND, // per tick
+                attack_duration: 5, // in ticks
+                attack_range: 420,
+                attack_damage: 14,
+                starting_health: 88,
+                crit_chance: 26, // out of 255
+                armor: 0,
+                magic_resist: 0,
+            },
+            Rarity::Epic => UnitStats {
+                movement_speed: 160 / TICKS_PER_SECOND, // per tick
+                attack_duration: 5, // in ticks
+                attack_range: 430,
+                attack_damage: 15,
+                starting_health: 92,
+                crit_chance: 30, // out of 255
+                armor: 0,
+                magic_resist: 0,
+            },
+            Rarity::Legendary => UnitStats {
+                movement_speed: 170 / TICKS_PER_SECOND, // per tick
+                attack_duration: 5, // in ticks
+                attack_range: 440,
+                attack_damage: 16,
+                starting_health: 96,
+                crit_chance: 33, // out of 255
+                armor: 0,
+                magic_resist: 0,
+            },
+            Rarity::Mythic => UnitStats {
+                movement_speed: 180 / TICKS_PER_SECOND, // per tick
+                attack_duration: 4, // in ticks
+                attack_range: 450,
+                attack_damage: 17,
+                starting_health: 100,
+                crit_chance: 37, // out of 255
+                armor: 0,
+                magic_resist: 0,
             },
         }),
         _ => None
     }
 }
 
+pub const MAX_STAR_LEVEL: u8 = 3;
+
+/// Baseline stats with health and damage scaled up for merged units
+pub fn get_star_unit_stats(ruleset_version: u16, unit_type: UnitType, rarity: Rarity, star_level: u8) -> Option<UnitStats> {
+    scale_star_stats(get_baseline_unit_stats(ruleset_version, unit_type, rarity)?, star_level)
+}
+
+/// Scale 1 star stats up to a star level
+pub fn scale_star_stats(stats: UnitStats, star_level: u8) -> Option<UnitStats> {
+    // percent of baseline health and damage at each star level
+    let multiplier: u32 = match star_level {
+        1 => 100,
+        2 => 180,
+        3 => 324,
+        _ => return None,
+    };
+    let mut stats = stats;
+    stats.starting_health = (stats.starting_health as u32 * multiplier / 100) as u16;
+    stats.attack_damage = (stats.attack_damage as u32 * multiplier / 100) as u16;
+    Some(stats)
+}
+
+fn default_star_level() -> u8 { 1 }
+
 /// unit "card" in a player's hand
 #[derive(Debug, Ord, Eq, PartialOrd, PartialEq, AnchorDeserialize, AnchorSerialize, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Card {
@@ -242,11 +404,67 @@ pub struct Card {
     pub stats: UnitStats,
     pub rarity: Rarity,
     pub special_trait: Option<SpecialTrait>,
+    /// 1 for a normal unit. Three units of the same type, rarity and star level merge into one of the next level.
+    #[serde(default = "default_star_level")]
+    pub star_level: u8,
+}
+
+impl Card {
+    /// Cards supplied by a player (rather than drawn on chain) must carry the stats for their type, rarity and star level
+    pub fn has_valid_stats(&self, ruleset: &Ruleset) -> bool {
+        match ruleset.get_unit_stats(self.unit_type, self.rarity, self.star_level) {
+            Some(stats) => stats == self.stats,
+            None => false,
+        }
+    }
+
+    /// Whether this card can be merged with other
+    pub fn merges_with(&self, other: &Card) -> bool {
+        self.unit_type == other.unit_type && self.rarity == other.rarity && self.star_level == other.star_level
+    }
+}
+
+/// Common one star card with the current baseline stats, shared by the test modules
+#[cfg(test)]
+pub fn test_card(unit_type: UnitType, special_trait: Option<SpecialTrait>) -> Card {
+    Card {
+        unit_type,
+        stats: get_baseline_unit_stats(CURRENT_RULESET_VERSION, unit_type, Rarity::Common).unwrap(),
+        rarity: Rarity::Common,
+        special_trait,
+        star_level: 1,
+    }
+}
+
+/// Merge three matching cards into one of the next star level. The first special trait found is kept.
+pub fn merge_cards(a: &Card, b: &Card, c: &Card, ruleset: &Ruleset) -> Option<Card> {
+    if !a.merges_with(b) || !a.merges_with(c) || a.star_level >= MAX_STAR_LEVEL {
+        return None;
+    }
+    let star_level = a.star_level + 1;
+    Some(Card {
+        unit_type: a.unit_type,
+        stats: ruleset.get_unit_stats(a.unit_type, a.rarity, star_level)?,
+        rarity: a.rarity,
+        special_trait: a.special_trait.or(b.special_trait).or(c.special_trait),
+        star_level,
+    })
 }
 
 #[derive(Debug, Ord, Eq, PartialOrd, PartialEq, AnchorDeserialize, AnchorSerialize, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub enum SpecialTrait {
+    /// Hops to the enemy back row at battle start, and vanishes for a moment after a crit
     Assassin,
+    /// Attacks hit harder the more health is missing, and a kill speeds up attacks
+    Berserker,
+    /// Shields nearby allies at battle start and again on death
+    Guardian,
+    /// Heals for part of the damage its attacks deal
+    Vampiric,
+    /// Reflects part of the damage from basic attacks back at the attacker
+    Thorns,
+    /// Dashes at the enemy on the first tick, and slows whatever it damages
+    Swift,
 }
 // pub struct AttackModifierArgs<'a> {
 //     pub unit: &'a Unit<'a>,
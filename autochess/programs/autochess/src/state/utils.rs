@@ -1,5 +1,6 @@
 use anchor_lang::{prelude::*};
 use serde;
+use super::fixed;
 
 #[derive(Debug, PartialEq, Default, AnchorDeserialize, AnchorSerialize, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub struct Location {
@@ -7,15 +8,24 @@ pub struct Location {
     pub y: u16,
 }
 impl Location {
+    /// Straight line distance, rounded down
     pub fn distance(&self, to: &Location) -> u16 {
-        let squares = (to.x as i32 - self.x as i32).pow(2) + (to.y as i32 - self.y as i32).pow(2);
-        (squares as f32).sqrt() as u16
+        let squares = (to.x as i64 - self.x as i64).pow(2) + (to.y as i64 - self.y as i64).pow(2);
+        fixed::isqrt(squares as u64) as u16
     }
 
+    /// Move amount along the line to a point, rounding each coordinate down
     pub fn move_towards(&self, to: &Location, precomputed_distance: u16, amount: u16) -> Location {
+        if precomputed_distance == 0 {
+            return *self;
+        }
+        let step = |from: u16, to: u16| {
+            let moved = from as i64 + fixed::mul_div_floor(to as i64 - from as i64, amount as i64, precomputed_distance as i64);
+            moved.clamp(0, u16::MAX as i64) as u16
+        };
         Location {
-            x: (self.x as f32 + (to.x as f32 - self.x as f32) /precomputed_distance as f32 * amount as f32) as u16,
-            y: (self.y as f32 + (to.y as f32 - self.y as f32) /precomputed_distance as f32 * amount as f32) as u16,
+            x: step(self.x, to.x),
+            y: step(self.y, to.y),
         }
     }
 
@@ -24,6 +24,10 @@ describe('autochess', async () => {
     program.programId
   ));
   const gamePDAKey = gamePDA[0]
+  const [unitRegistryPDAKey] = await anchor.web3.PublicKey.findProgramAddress(
+    [Buffer.from("UnitRegistry")],
+    program.programId
+  );
   const initializerReveal1 = hash('random1');
   const initializerSecret1 = hash('secret1');
   const initializerCommitment1Unhashed = Buffer.from([...Buffer.from(initializerReveal1, 'hex'), ...Buffer.from(initializerSecret1, 'hex')]).toString('hex');
@@ -68,6 +72,7 @@ describe('autochess', async () => {
         accounts: {
           game: gamePDAKey,
           initializer: program.provider.publicKey,
+          unitRegistry: unitRegistryPDAKey,
           systemProgram: anchor.web3.SystemProgram.programId,
         },
     });
@@ -101,6 +106,7 @@ describe('autochess', async () => {
           accounts: {
             game: pdaKey,
             initializer: program.provider.publicKey,
+            unitRegistry: unitRegistryPDAKey,
             systemProgram: anchor.web3.SystemProgram.programId,
           },
       });
@@ -142,6 +148,7 @@ describe('autochess', async () => {
         accounts: {
           game: canceledGameKey,
           initializer: program.provider.publicKey,
+          unitRegistry: unitRegistryPDAKey,
           systemProgram: anchor.web3.SystemProgram.programId,
         },
     });
@@ -577,6 +584,7 @@ describe('autochess', async () => {
         accounts: {
           game: inactiveGameKey,
           initializer: program.provider.publicKey,
+          unitRegistry: unitRegistryPDAKey,
           systemProgram: anchor.web3.SystemProgram.programId,
         },
     });
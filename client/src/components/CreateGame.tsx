@@ -39,6 +39,10 @@ export const CreateGame: FC = () => {
             program.programId
         );
         const gamePDAKey = gamePDA[0];
+        const [unitRegistryPDAKey] = await anchor.web3.PublicKey.findProgramAddress(
+            [Buffer.from('UnitRegistry')],
+            program.programId
+        );
 
         let signature = '';
         try {
@@ -64,6 +68,7 @@ export const CreateGame: FC = () => {
                     accounts: {
                         game: gamePDAKey,
                         initializer: program.provider.publicKey,
+                        unitRegistry: unitRegistryPDAKey,
                         systemProgram: SystemProgram.programId,
                     },
                     postInstructions: [
@@ -62,6 +62,14 @@ export const UnitData: FC<UnitDataProps> = (props: UnitDataProps) => {
                 return (
                     <div className='w-fit border-2 border-red-300 text-red-300 px-2'>Assassin</div>
                 )
+            case SpecialTrait.Berserker:
+            case SpecialTrait.Guardian:
+            case SpecialTrait.Vampiric:
+            case SpecialTrait.Thorns:
+            case SpecialTrait.Swift:
+                return (
+                    <div className='w-fit border-2 border-red-300 text-red-300 px-2'>{specialTrait}</div>
+                )
             default:
                 return null;
         }
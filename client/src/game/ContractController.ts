@@ -11,7 +11,7 @@ import { getProgram } from "utils/program";
 import { GameProgress } from "./Utils";
 import BN from 'bn.js';
 import Game from "./Game";
-import { draw_private_hand, UnitTypeWasm } from "wasm-client";
+import { UnitTypeWasm } from "wasm-client";
 import EntityManager from "./EntityManager";
 import { UIComponent, UIController, UIReducerAction } from "pages/play/[id]";
 import { UnitStats } from "models/gameTypes";
@@ -127,6 +127,9 @@ class ContractController {
                 throw new Error('you are not a player in this game');
             }
 
+            // hands and the battle preview follow the rules the game was created with
+            this.entityManager.loadGameRules(account.rulesetVersion, account.unitDefinitions, account.registryVersion);
+
             let x = 50, y = 45
             if (innerHeight > innerWidth) {
                 // zoom out on mobile devices
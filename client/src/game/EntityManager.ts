@@ -9,7 +9,7 @@ import { Group } from "@tweenjs/tween.js";
 
 import DraggableEntity from "./DraggableEntity";
 import HiddenEntity from "./HiddenEntity";
-import { RawCard, RawCardAnchor, UnitStats } from "models/gameTypes";
+import { RawCard, RawCardAnchor, UnitDefinitionAnchor, UnitStats } from "models/gameTypes";
 
 
 class EntityManager {
@@ -85,6 +85,10 @@ class EntityManager {
         this.wasmController = wasmController;
     }
 
+    public loadGameRules(rulesetVersion: number, unitDefinitions: Array<UnitDefinitionAnchor>, registryVersion: number) {
+        this.wasmController.loadGameRules(rulesetVersion, unitDefinitions, registryVersion);
+    }
+
     public updateGame() {
         this.wasmController.step();
         const entities: Array<any> = this.wasmController.getEntities().all;
@@ -1,5 +1,5 @@
 import assert from "assert";
-import { RarityLevel, RawCard, RawCardAnchor, SpecialTrait, UnitStats } from "models/gameTypes";
+import { RarityLevel, RawCard, RawCardAnchor, RawUnitDefinition, SpecialTrait, UnitDefinitionAnchor, UnitStats } from "models/gameTypes";
 import { Bone, BoxBufferGeometry, Object3D, SkinnedMesh, Vector2, Vector3 } from "three"
 import { ControllerWasm, UnitTypeWasm } from "wasm-client";
 
@@ -91,26 +91,50 @@ export const parseSpecialTraitFromAnchor = (specialTrait: string | {[key:string]
     switch(text) {
         case 'assassin':
             return SpecialTrait.Assassin;
+        case 'berserker':
+            return SpecialTrait.Berserker;
+        case 'guardian':
+            return SpecialTrait.Guardian;
+        case 'vampiric':
+            return SpecialTrait.Vampiric;
+        case 'thorns':
+            return SpecialTrait.Thorns;
+        case 'swift':
+            return SpecialTrait.Swift;
         default:
             throw new Error('should never get here');
     }
 }
 
+const convertAnchorStats = (stats: RawCardAnchor['stats']): RawCard['stats'] => {
+    return {
+        attack_damage: stats.attackDamage,
+        attack_duration: stats.attackDuration,
+        attack_range: stats.attackRange,
+        crit_chance: stats.critChance,
+        movement_speed: stats.movementSpeed,
+        starting_health: stats.startingHealth,
+        armor: stats.armor,
+        magic_resist: stats.magicResist,
+    }
+}
+
 export const convertAnchorCardToRawCard = (anchorCard: RawCardAnchor): RawCard => {
     return {
-        stats: {
-            attack_damage: anchorCard.stats.attackDamage,
-            attack_duration: anchorCard.stats.attackDuration,
-            attack_range: anchorCard.stats.attackRange,
-            crit_chance: anchorCard.stats.critChance,
-            movement_speed: anchorCard.stats.movementSpeed,
-            starting_health: anchorCard.stats.startingHealth,
-        },
+        stats: convertAnchorStats(anchorCard.stats),
         unit_type: capitalize(Object.keys(anchorCard.unitType)[0]), 
         rarity: capitalize(Object.keys(anchorCard.rarity)[0]),
         special_trait: anchorCard.specialTrait ? capitalize(Object.keys(anchorCard.specialTrait)[0]) : null,
     }
 }
+
+export const convertAnchorUnitDefinition = (definition: UnitDefinitionAnchor): RawUnitDefinition => {
+    return {
+        unit_type: capitalize(Object.keys(definition.unitType)[0]),
+        rarity: capitalize(Object.keys(definition.rarity)[0]),
+        stats: convertAnchorStats(definition.stats),
+    }
+}
 function capitalize(s: string) {
     return s.charAt(0).toUpperCase() + s.slice(1);
 }
@@ -1,6 +1,6 @@
-import { RawCard, RawCardAnchor, UnitStats } from "models/gameTypes";
-import { ControllerWasm, UnitTypeWasm, WasmState, draw_private_hand } from "wasm-client";
-import { parseRarityFromAnchor, parseRawCard, parseUnitTypeFromAnchor } from "./Utils";
+import { RawCard, RawCardAnchor, UnitDefinitionAnchor, UnitStats } from "models/gameTypes";
+import { ControllerWasm, UnitTypeWasm, WasmState } from "wasm-client";
+import { convertAnchorUnitDefinition, parseRarityFromAnchor, parseRawCard, parseUnitTypeFromAnchor } from "./Utils";
 
 class WasmController {
     private wasmState: WasmState;
@@ -9,8 +9,21 @@ class WasmController {
         this.wasmState = new WasmState();
     }
 
+    /**
+     * Simulate and draw hands with the game's ruleset version and the unit registry it snapshotted, if any
+     */
+    public loadGameRules(rulesetVersion: number, unitDefinitions: Array<UnitDefinitionAnchor>, registryVersion: number) {
+        if (!this.wasmState.set_ruleset_version(rulesetVersion)) {
+            throw new Error(`unsupported ruleset version ${rulesetVersion}`);
+        }
+        if (unitDefinitions.length > 0
+            && !this.wasmState.load_unit_registry(unitDefinitions.map(convertAnchorUnitDefinition), registryVersion)) {
+            throw new Error('invalid unit registry');
+        }
+    }
+
     public drawHand(handSize: number, randomness1: Uint8Array, randomness2: Uint8Array): Array<UnitStats> {
-        const cardHand: Array<RawCard> = draw_private_hand(handSize, randomness1, randomness2);
+        const cardHand: Array<RawCard> = this.wasmState.draw_private_hand(handSize, randomness1, randomness2);
         console.log(cardHand);
         const hand: Array<UnitStats> = [];
         for (const card of cardHand) {
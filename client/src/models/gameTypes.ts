@@ -11,6 +11,11 @@ export enum RarityLevel {
 
 export enum SpecialTrait {
     Assassin = "Assassin",
+    Berserker = "Berserker",
+    Guardian = "Guardian",
+    Vampiric = "Vampiric",
+    Thorns = "Thorns",
+    Swift = "Swift",
     None = "None",
 }
   
@@ -35,6 +40,8 @@ export type RawCard = {
         crit_chance: number
         movement_speed: number
         starting_health: number
+        armor: number
+        magic_resist: number
     },
     unit_type: string,
     rarity: string,
@@ -49,6 +56,8 @@ export type RawCardAnchor = {
         critChance: number
         movementSpeed: number
         startingHealth: number
+        armor: number
+        magicResist: number
     }, 
     unitType: {
         [key:string]: any
@@ -59,4 +68,20 @@ export type RawCardAnchor = {
     specialTrait: {
         [key:string]: any
     } | null,
+}
+
+export type RawUnitDefinition = {
+    unit_type: string,
+    rarity: string,
+    stats: RawCard['stats'],
+}
+
+export type UnitDefinitionAnchor = {
+    unitType: {
+        [key:string]: any
+    },
+    rarity: {
+        [key:string]: any
+    },
+    stats: RawCardAnchor['stats'],
 }
\ No newline at end of file
@@ -4,9 +4,10 @@ mod utils;
 use std::{collections::{BTreeMap}, convert::TryInto};
 
 use wasm_bindgen::prelude::*;
+use anchor_lang::prelude::Pubkey;
 use serde;
 
-use autochess::state::{game::Game, game::draw_hand, units::{self, UnitType, UnitStats, Card}, entities::Controller};
+use autochess::state::{game::Game, game::{draw_hand, draw_hand_from_deck, MULLIGAN_LIMIT}, deck::DeckCard, units::{self, UnitType, UnitStats, Card}, entities::Controller, merkle::{self, CardReveal, AssetReveal}, synergies, registry::{self, UnitDefinition}, ruleset};
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
 #[cfg(feature = "wee_alloc")]
@@ -35,7 +36,8 @@ pub struct WasmLocation {
 pub enum UnitTypeWasm {
     Wolf,
     Bear,
-    Bull
+    Bull,
+    Hawk,
 }
 impl UnitTypeWasm {
     fn convert(&self) -> UnitType {
@@ -43,6 +45,7 @@ impl UnitTypeWasm {
             UnitTypeWasm::Wolf => UnitType::Wolf,
             UnitTypeWasm::Bear => UnitType::Bear,
             UnitTypeWasm::Bull => UnitType::Bull,
+            UnitTypeWasm::Hawk => UnitType::Hawk,
         }
     }
 }
@@ -122,15 +125,128 @@ impl WasmState {
         JsValue::from_serde(&self.game.entities).unwrap()
     }
 
+    /// Stats of an entity after speed and attack speed multipliers
+    pub fn get_effective_stats(&mut self, id: u16) -> JsValue {
+        JsValue::from_serde(&self.game.entities.get_by_id(id).and_then(|entity| entity.get_effective_stats())).unwrap()
+    }
+
+    pub fn get_projectiles(&mut self) -> JsValue {
+        JsValue::from_serde(&self.game.projectiles).unwrap()
+    }
+
     pub fn get_entity_by_id(&mut self, id: u16) -> JsValue {
         JsValue::from_serde(&self.game.entities.get_by_id_mut(id)).unwrap()
     }
+
+    /// Simulate under the rules of an older game, for replays. Returns false if the version is unknown.
+    pub fn set_ruleset_version(&mut self, ruleset_version: u16) -> bool {
+        if !ruleset::is_supported(ruleset_version) {
+            return false;
+        }
+        self.game.ruleset_version = ruleset_version;
+        true
+    }
+
+    /// Use the unit definitions from the on chain unit registry, so simulation matches games that snapshotted it.
+    /// Returns false if the definitions don't cover every unit type and rarity exactly once.
+    pub fn load_unit_registry(&mut self, definitions_js: JsValue, version: u32) -> bool {
+        let definitions: Vec<UnitDefinition> = match definitions_js.into_serde() {
+            Ok(definitions) => definitions,
+            Err(_) => return false,
+        };
+        if !registry::validate_definitions(&definitions) {
+            return false;
+        }
+        self.game.unit_definitions = definitions;
+        self.game.registry_version = version;
+        true
+    }
+
+    /// Draw a random hand with the stats of the game's ruleset and loaded unit registry
+    pub fn draw_private_hand(&self, hand_size: u8, finished_reveal_1: &[u8], player_reveal_2: &[u8]) -> JsValue {
+        JsValue::from_serde(
+            &draw_hand(hand_size, finished_reveal_1.try_into().expect("slice with incorrect length"),
+                player_reveal_2.try_into().expect("slice with incorrect length"),
+                &self.game.ruleset()
+            )).unwrap()
+    }
+
+    /// Draw a hand from a deck with the stats of the game's ruleset and loaded unit registry
+    pub fn draw_private_hand_from_deck(&self, hand_size: u8, finished_reveal_1: &[u8], player_reveal_2: &[u8], deck_js: JsValue) -> JsValue {
+        let deck: Vec<DeckCard> = deck_js.into_serde().unwrap();
+        JsValue::from_serde(
+            &draw_hand_from_deck(hand_size, finished_reveal_1.try_into().expect("slice with incorrect length"),
+                player_reveal_2.try_into().expect("slice with incorrect length"),
+                &deck,
+                &self.game.ruleset()
+            )).unwrap()
+    }
+
+    /// Synergies a player currently has from their fielded units
+    pub fn get_active_synergies(&mut self, player_type: ControllerWasm) -> JsValue {
+        JsValue::from_serde(&synergies::get_active_synergies(&self.game.entities, player_type.convert())).unwrap()
+    }
+
+    /// Units a player has lost in the battle so far
+    pub fn get_units_lost(&self, player_type: ControllerWasm) -> u8 {
+        self.game.entities.count_lost(player_type.convert())
+    }
 }
 
+/// Number of replacement cards available after the end of a random hand
 #[wasm_bindgen]
-pub fn draw_private_hand(hand_size: u8, finished_reveal_1: &[u8], player_reveal_2: &[u8]) -> JsValue {
-    JsValue::from_serde(
-        &draw_hand(hand_size, finished_reveal_1.try_into().expect("slice with incorrect length"), 
-            player_reveal_2.try_into().expect("slice with incorrect length")
-        )).unwrap()
-}
\ No newline at end of file
+pub fn get_mulligan_limit() -> u8 {
+    MULLIGAN_LIMIT
+}
+
+fn hand_leaves(hand: &Vec<Card>, salts: &[u8]) -> Vec<[u8; 32]> {
+    hand.iter().enumerate()
+        .map(|(i, card)| merkle::hash_card_leaf(i as u8, card, salts[i * 32..(i + 1) * 32].try_into().expect("not enough salt")))
+        .collect()
+}
+
+/// Merkle root of a hand for committed hand mode. salts holds 32 bytes per card, in hand order.
+#[wasm_bindgen]
+pub fn get_hand_root(hand_js: JsValue, salts: &[u8]) -> Vec<u8> {
+    let hand: Vec<Card> = hand_js.into_serde().unwrap();
+    merkle::compute_root(&hand_leaves(&hand, salts)).to_vec()
+}
+
+/// Card and proof to send with reveal_second_committed for one placed hand position
+#[wasm_bindgen]
+pub fn get_card_reveal(hand_js: JsValue, salts: &[u8], hand_position: u8) -> JsValue {
+    let hand: Vec<Card> = hand_js.into_serde().unwrap();
+    let index = hand_position as usize;
+    let reveal = CardReveal {
+        hand_position,
+        card: hand[index].clone(),
+        salt: salts[index * 32..(index + 1) * 32].try_into().expect("not enough salt"),
+        proof: merkle::build_proof(&hand_leaves(&hand, salts), index),
+    };
+    JsValue::from_serde(&reveal).unwrap()
+}
+
+fn asset_leaves(assets: &[u8], salts: &[u8]) -> Vec<[u8; 32]> {
+    assets.chunks(32).enumerate()
+        .map(|(i, asset)| merkle::hash_asset_leaf(i as u8, &Pubkey::new(asset), salts[i * 32..(i + 1) * 32].try_into().expect("not enough salt")))
+        .collect()
+}
+
+/// Merkle root of a collection hand. assets holds the 32 byte card asset address of each card, in hand order.
+#[wasm_bindgen]
+pub fn get_collection_hand_root(assets: &[u8], salts: &[u8]) -> Vec<u8> {
+    merkle::compute_root(&asset_leaves(assets, salts)).to_vec()
+}
+
+/// Asset and proof to send with reveal_second_collection for one placed hand position
+#[wasm_bindgen]
+pub fn get_asset_reveal(assets: &[u8], salts: &[u8], hand_position: u8) -> JsValue {
+    let index = hand_position as usize;
+    let reveal = AssetReveal {
+        hand_position,
+        asset: Pubkey::new(&assets[index * 32..(index + 1) * 32]),
+        salt: salts[index * 32..(index + 1) * 32].try_into().expect("not enough salt"),
+        proof: merkle::build_proof(&asset_leaves(assets, salts), index),
+    };
+    JsValue::from_serde(&reveal).unwrap()
+}
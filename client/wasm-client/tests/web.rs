@@ -3,7 +3,10 @@
 #![cfg(target_arch = "wasm32")]
 
 extern crate wasm_bindgen_test;
+use wasm_bindgen::JsValue;
 use wasm_bindgen_test::*;
+use autochess::state::{registry, test_vectors::{PINNED_OUTCOMES, seeded_outcome_hash}};
+use wasm_client::WasmState;
 
 wasm_bindgen_test_configure!(run_in_browser);
 
@@ -11,3 +14,20 @@ wasm_bindgen_test_configure!(run_in_browser);
 fn pass() {
     assert_eq!(1 + 1, 2);
 }
+
+/// The program's seeded battle vectors, so the preview in the browser can't drift from the chain
+#[wasm_bindgen_test]
+fn seeded_battles_match_the_program() {
+    for (ruleset_version, pinned) in PINNED_OUTCOMES {
+        assert_eq!(seeded_outcome_hash(ruleset_version), pinned, "ruleset version {}", ruleset_version);
+    }
+}
+
+#[wasm_bindgen_test]
+fn load_unit_registry_rejects_incomplete_definitions() {
+    let mut state = WasmState::new();
+    let mut definitions = registry::default_unit_definitions();
+    assert!(state.load_unit_registry(JsValue::from_serde(&definitions).unwrap(), 1));
+    definitions.pop();
+    assert!(!state.load_unit_registry(JsValue::from_serde(&definitions).unwrap(), 2));
+}